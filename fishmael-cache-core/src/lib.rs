@@ -26,7 +26,7 @@ pub trait Cacheable: RedisKeyProvider + RedisFieldProvider {
         Self: Sized
     {
         redis::cmd("HSET")
-            .arg(&self.get_key())
+            .arg(self.get_key())
             .args_from(self)
             .exec_async(con)
             .await
@@ -39,7 +39,7 @@ pub struct StreamableCommandInteraction {
 
 impl RedisStreamKeyProvider for StreamableCommandInteraction {
     fn get_stream_key(&self, shard: &ShardId) -> String {
-        return format!("command_interaction:{}", shard.number())
+        format!("command_interaction:{}", shard.number())
     }
 }
 
@@ -29,7 +29,7 @@ pub struct StreamableComponentInteraction {
 
 impl RedisStreamKeyProvider for StreamableComponentInteraction {
     fn get_stream_key(&self, shard: &ShardId) -> String {
-        return format!("component_interaction:{}", shard.number())
+        format!("component_interaction:{}", shard.number())
     }
 }
 
@@ -0,0 +1,106 @@
+use rand::Rng;
+use std::time::Duration;
+
+
+/// Delay policy for reconnecting after failed connection attempts.
+///
+/// The delay before attempt `n` is picked uniformly between zero and
+/// `min(cap, base * 2^(n - 1))` ("full jitter"), so shards that lost their
+/// connection at the same time don't reconnect in lockstep.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Backoff {
+    base: Duration,
+    cap: Duration,
+    max_attempts: Option<u8>,
+}
+
+
+impl Backoff {
+    pub const fn new(base: Duration, cap: Duration) -> Self {
+        Self {
+            base,
+            cap,
+            max_attempts: None,
+        }
+    }
+
+    /// Give up and close the shard fatally after this many failed attempts.
+    pub const fn with_max_attempts(mut self, max_attempts: u8) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    pub const fn base(&self) -> Duration {
+        self.base
+    }
+
+    pub const fn cap(&self) -> Duration {
+        self.cap
+    }
+
+    pub const fn max_attempts(&self) -> Option<u8> {
+        self.max_attempts
+    }
+
+    /// Random delay before the given reconnect attempt.
+    pub(crate) fn delay(&self, attempt: u8, rng: &mut impl Rng) -> Duration {
+        let exponent = u32::from(attempt.saturating_sub(1)).min(31);
+        let ceiling = self.base
+            .checked_mul(1 << exponent)
+            .map_or(self.cap, |delay| delay.min(self.cap));
+
+        ceiling.mul_f64(rng.gen_range(0.0..=1.0))
+    }
+
+    /// Whether no attempts are left after `attempts` failed ones.
+    pub(crate) fn is_exhausted(&self, attempts: u8) -> bool {
+        self.max_attempts.is_some_and(|max| attempts >= max)
+    }
+}
+
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), Duration::from_secs(120))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn delay_is_within_ceiling() {
+        let backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(10));
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for (attempt, ceiling) in [(1, 1), (2, 2), (3, 4), (4, 8), (5, 10), (6, 10)] {
+            for _ in 0..100 {
+                assert!(backoff.delay(attempt, &mut rng) <= Duration::from_secs(ceiling));
+            }
+        }
+    }
+
+    #[test]
+    fn delay_is_capped_for_many_attempts() {
+        let backoff = Backoff::default();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for attempt in [32, 64, u8::MAX] {
+            assert!(backoff.delay(attempt, &mut rng) <= backoff.cap());
+        }
+    }
+
+    #[test]
+    fn is_exhausted_after_max_attempts() {
+        let unlimited = Backoff::default();
+        assert!(!unlimited.is_exhausted(u8::MAX));
+
+        let limited = Backoff::default().with_max_attempts(3);
+        assert!(!limited.is_exhausted(2));
+        assert!(limited.is_exhausted(3));
+        assert!(limited.is_exhausted(4));
+    }
+}
@@ -0,0 +1,76 @@
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use twilight_model::gateway::connection_info::BotConnectionInfo;
+
+use crate::{
+    config::API_VERSION,
+    error::{GatewayInfoError, GatewayInfoErrorKind},
+};
+
+
+/// Default base URL of Discord's HTTP API.
+pub const API_URL: &str = "https://discord.com/api";
+
+
+/// Minimal HTTP client for the gateway's endpoints.
+#[derive(Clone)]
+pub struct GatewayClient {
+    api_url: String,
+    http: reqwest::Client,
+    token: String,
+}
+
+
+impl GatewayClient {
+    pub fn new(token: String) -> Self {
+        Self::with_api_url(token, API_URL.to_owned())
+    }
+
+    /// Send requests to this base URL instead of Discord's API, e.g. a stub.
+    pub fn with_api_url(token: String, api_url: String) -> Self {
+        Self {
+            api_url: api_url.trim_end_matches('/').to_owned(),
+            http: reqwest::Client::new(),
+            token,
+        }
+    }
+
+    pub fn api_url(&self) -> &str {
+        &self.api_url
+    }
+
+    /// Recommended shard count, gateway URL and session start limit of the bot.
+    pub async fn bot_info(&self) -> Result<BotConnectionInfo, GatewayInfoError> {
+        let response = self.http
+            .get(format!("{}/v{}/gateway/bot", self.api_url, API_VERSION))
+            .header("Authorization", format!("Bot {}", self.token))
+            .send()
+            .await
+            .map_err(|source| GatewayInfoError {
+                kind: GatewayInfoErrorKind::Requesting,
+                source: Some(Box::new(source)),
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(GatewayInfoError {
+                kind: GatewayInfoErrorKind::Status { status: status.as_u16() },
+                source: None,
+            });
+        }
+
+        response.json().await.map_err(|source| GatewayInfoError {
+            kind: GatewayInfoErrorKind::Deserializing,
+            source: Some(Box::new(source)),
+        })
+    }
+}
+
+
+impl Debug for GatewayClient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("GatewayClient")
+            .field("api_url", &self.api_url)
+            .field("token", &"<redacted>")
+            .finish()
+    }
+}
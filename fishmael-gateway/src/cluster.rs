@@ -0,0 +1,320 @@
+use futures::StreamExt;
+use futures_core::Stream;
+use std::{
+    mem,
+    ops::Range,
+    pin::Pin,
+    task::{Context as AsyncContext, Poll},
+};
+use twilight_model::gateway::{event::Event, CloseFrame, ShardId};
+
+use crate::{
+    config::ShardConfig,
+    error::ReceiveError,
+    message::Message,
+    poll_event::event_from_message,
+    Shard,
+    ShardState,
+};
+
+
+/// Shards of one bot running in a single process.
+///
+/// Polls all shards concurrently, merging their messages into one stream.
+pub struct ShardCluster {
+    config: ShardConfig,
+    /// Index of the shard polled first, so busy shards don't starve the others.
+    next: usize,
+    /// Shards at a new total, loading in the background until they replace `shards`.
+    resharding: Option<Vec<Shard>>,
+    shards: Vec<Shard>,
+}
+
+
+impl ShardCluster {
+    /// Create the shards in `ids` out of `total` shards, sharing one config.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an ID isn't lower than `total`.
+    pub fn new(config: ShardConfig, ids: Range<u64>, total: u64) -> Self {
+        let shards = ids
+            .map(|id| Shard::with_config(ShardId::new(id, total), config.clone()))
+            .collect();
+
+        Self {
+            config,
+            next: 0,
+            resharding: None,
+            shards,
+        }
+    }
+
+    pub fn config(&self) -> &ShardConfig {
+        &self.config
+    }
+
+    pub fn shard(&self, shard_id: ShardId) -> Option<&Shard> {
+        self.shards.iter().find(|shard| shard.id() == shard_id)
+    }
+
+    pub fn shard_mut(&mut self, shard_id: ShardId) -> Option<&mut Shard> {
+        self.shards.iter_mut().find(|shard| shard.id() == shard_id)
+    }
+
+    pub fn shards(&self) -> impl Iterator<Item = &Shard> {
+        self.shards.iter()
+    }
+
+    /// Current state of every shard.
+    pub fn states(&self) -> impl Iterator<Item = (ShardId, ShardState)> + '_ {
+        self.shards.iter().map(|shard| (shard.id(), shard.state()))
+    }
+
+    /// Close a shard and replace it with a fresh one, which identifies anew.
+    ///
+    /// Returns `false` if the cluster has no shard with this ID.
+    pub async fn restart(&mut self, shard_id: ShardId) -> bool {
+        let config = self.config.clone();
+        let Some(shard) = self.shard_mut(shard_id) else {
+            return false;
+        };
+
+        shard.close(CloseFrame::NORMAL).await;
+        *shard = Shard::with_config(shard_id, config);
+        true
+    }
+
+    /// Start the shards in `ids` out of a new `total` in the background.
+    ///
+    /// Each new shard's messages are discarded until it is active and
+    /// received every guild announced by its READY, and are passed through
+    /// from then on. Once all new shards got there, the stream switches over
+    /// to them, and the old ones are closed.
+    ///
+    /// Events a new shard receives before it loaded are still delivered by
+    /// the old shards. From then until the switch-over, the events of its
+    /// guilds arrive twice, from it and from an old shard, so consumers have
+    /// to tolerate duplicates rather than lost events.
+    ///
+    /// Unlike [`Shard::is_loaded`], the guild load timeout doesn't apply, so
+    /// no guild goes missing in the switch-over. Guilds in an outage don't
+    /// hold it up, since they arrive as unavailable GUILD_CREATEs. Starting another reshard closes
+    /// the shards of the one in progress.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an ID isn't lower than `total`.
+    pub fn reshard(&mut self, ids: Range<u64>, total: u64) {
+        let shards = ids
+            .map(|id| Shard::with_config(ShardId::new(id, total), self.config.clone()))
+            .collect();
+
+        if let Some(abandoned) = self.resharding.replace(shards) {
+            close_in_background(abandoned);
+        }
+    }
+
+    pub fn is_resharding(&self) -> bool {
+        self.resharding.is_some()
+    }
+
+    /// Drive the new shards of a reshard, switching over once all have loaded.
+    ///
+    /// Returns a message of a new shard that already loaded.
+    fn poll_resharding(&mut self, cx: &mut AsyncContext<'_>) -> Option<(ShardId, Result<Message, ReceiveError>)> {
+        let shards = self.resharding.as_mut()?;
+
+        let received_all_guilds = |shard: &Shard| {
+            shard.state() == ShardState::Active && shard.pending_guilds().is_empty()
+        };
+
+        for shard in shards.iter_mut() {
+            // Until then the old shards still deliver these guilds' events.
+            let mut loaded = received_all_guilds(shard);
+
+            while let Poll::Ready(item) = shard.poll_next_unpin(cx) {
+                let Some(item) = item else {
+                    println!("shard {} ended while resharding; abandoning reshard", shard.id());
+
+                    close_in_background(self.resharding.take().unwrap());
+                    return None;
+                };
+
+                if loaded {
+                    return Some((shard.id(), item));
+                }
+                loaded = received_all_guilds(shard);
+            }
+        }
+
+        if !shards.iter().all(received_all_guilds) {
+            return None;
+        }
+
+        println!("resharding complete; switching over to {} shards", shards.len());
+
+        let shards = self.resharding.take().unwrap();
+        let old = mem::replace(&mut self.shards, shards);
+        self.next = 0;
+
+        close_in_background(old);
+        None
+    }
+
+    /// Close every shard, ending the stream.
+    pub async fn close(&mut self, frame: CloseFrame<'static>) {
+        let mut resharding = self.resharding.take().unwrap_or_default();
+
+        for shard in self.shards.iter_mut().chain(&mut resharding) {
+            shard.close(frame.clone()).await;
+        }
+    }
+
+    /// Next event of any shard, with the ID of the shard that received it.
+    pub async fn next_event(&mut self) -> Option<(ShardId, Result<Event, ReceiveError>)> {
+        loop {
+            let (shard_id, item) = self.next().await?;
+
+            let event_types = self.config.event_types();
+            match item.and_then(|message| event_from_message(message, event_types)) {
+                Ok(Some(event)) => return Some((shard_id, Ok(event))),
+                Ok(None) => {},
+                Err(err) => return Some((shard_id, Err(err))),
+            }
+        }
+    }
+}
+
+
+/// Close shards that are no longer polled, without holding up the cluster.
+fn close_in_background(shards: Vec<Shard>) {
+    tokio::spawn(async move {
+        for mut shard in shards {
+            shard.close(CloseFrame::NORMAL).await;
+        }
+    });
+}
+
+
+impl Stream for ShardCluster {
+    type Item = (ShardId, Result<Message, ReceiveError>);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut AsyncContext<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(item) = self.poll_resharding(cx) {
+            return Poll::Ready(Some(item));
+        }
+
+        let len = self.shards.len();
+        let mut ended = 0;
+
+        for offset in 0..len {
+            let index = (self.next + offset) % len;
+            let shard = &mut self.shards[index];
+
+            match shard.poll_next_unpin(cx) {
+                Poll::Ready(Some(item)) => {
+                    let shard_id = shard.id();
+                    self.next = (index + 1) % len;
+
+                    return Poll::Ready(Some((shard_id, item)));
+                },
+                Poll::Ready(None) => ended += 1,
+                Poll::Pending => {},
+            }
+        }
+
+        if ended == len {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tests::{channel_transport, ChannelConnector, HELLO, READY},
+        Intents,
+        ShardBuilder,
+    };
+    use futures::channel::mpsc::UnboundedSender;
+    use std::time::Duration;
+    use tokio::time;
+    use tokio_tungstenite::tungstenite::{Error as WebsocketError, Message as WebsocketMessage};
+
+    const GUILD_CREATE: &str = r#"{"op":0,"s":2,"t":"GUILD_CREATE","d":{"id":"1"}}"#;
+    const EARLY_DISPATCH: &str = r#"{"op":0,"s":3,"t":"TYPING_START","d":{}}"#;
+    const LATE_DISPATCH: &str = r#"{"op":0,"s":4,"t":"TYPING_START","d":{}}"#;
+
+    fn send(gateway_tx: &UnboundedSender<Result<WebsocketMessage, WebsocketError>>, json: &str) {
+        gateway_tx.unbounded_send(Ok(WebsocketMessage::Text(json.into()))).unwrap();
+    }
+
+    /// Text messages passed through until the cluster goes quiet.
+    async fn received(cluster: &mut ShardCluster) -> Vec<(ShardId, String)> {
+        let mut received = Vec::new();
+        while let Ok(Some((shard_id, item))) = time::timeout(Duration::from_secs(1), cluster.next()).await {
+            if let Ok(Message::Text(json)) = item {
+                received.push((shard_id, json));
+            }
+        }
+
+        received
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn passes_through_loaded_shards_while_resharding() {
+        let (old, (old_tx, mut old_rx)) = channel_transport();
+        let (first, (first_tx, _first_rx)) = channel_transport();
+        let (second, (second_tx, _second_rx)) = channel_transport();
+        let config = ShardBuilder::new("token".into(), Intents::GUILDS)
+            .connector(ChannelConnector::new([old, first, second]))
+            .into_config()
+            .unwrap();
+
+        let old_id = ShardId::new(0, 1);
+        let mut cluster = ShardCluster::new(config, 0..1, 1);
+        send(&old_tx, HELLO);
+        send(&old_tx, READY);
+        assert_eq!(received(&mut cluster).await.len(), 2);
+
+        let (first_id, second_id) = (ShardId::new(0, 2), ShardId::new(1, 2));
+        cluster.reshard(0..2, 2);
+
+        // Discarded while loading, as the old shard delivers it.
+        send(&first_tx, HELLO);
+        send(&first_tx, &READY.replace(r#""guilds":[]"#, r#""guilds":[{"id":"1","unavailable":true}]"#));
+        send(&first_tx, EARLY_DISPATCH);
+        send(&old_tx, EARLY_DISPATCH);
+        // Passed through once loaded, before the switch-over.
+        send(&first_tx, GUILD_CREATE);
+        send(&first_tx, LATE_DISPATCH);
+        send(&old_tx, LATE_DISPATCH);
+
+        let mut messages = received(&mut cluster).await;
+        messages.sort_by_key(|(shard_id, json)| (shard_id.total(), json.clone()));
+        assert_eq!(messages, [
+            (old_id, EARLY_DISPATCH.to_owned()),
+            (old_id, LATE_DISPATCH.to_owned()),
+            (first_id, LATE_DISPATCH.to_owned()),
+        ]);
+        assert!(cluster.is_resharding(), "the second shard hasn't loaded yet");
+
+        send(&second_tx, HELLO);
+        send(&second_tx, READY);
+        assert!(received(&mut cluster).await.is_empty());
+        assert!(!cluster.is_resharding());
+        assert_eq!(cluster.shards().map(Shard::id).collect::<Vec<_>>(), [first_id, second_id]);
+
+        let mut closed = false;
+        while let Some(message) = old_rx.next().await {
+            closed |= matches!(message, WebsocketMessage::Close(_));
+        }
+        assert!(closed, "the old shard is closed");
+    }
+}
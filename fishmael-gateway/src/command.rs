@@ -0,0 +1,28 @@
+use serde::Serialize;
+use twilight_model::gateway::payload::outgoing::{
+    RequestGuildMembers,
+    UpdatePresence,
+    UpdateVoiceState,
+};
+
+
+mod private {
+    use super::*;
+
+    pub trait Sealed {}
+
+    impl Sealed for RequestGuildMembers {}
+    impl Sealed for UpdatePresence {}
+    impl Sealed for UpdateVoiceState {}
+}
+
+
+/// Gateway payload that users may send over a shard's connection.
+///
+/// Heartbeats, identifies and resumes are managed by the shard itself and
+/// are therefore not commands.
+pub trait Command: Serialize + private::Sealed {}
+
+impl Command for RequestGuildMembers {}
+impl Command for UpdatePresence {}
+impl Command for UpdateVoiceState {}
@@ -0,0 +1,233 @@
+use flate2::{Decompress, DecompressError, FlushDecompress};
+use std::error::Error;
+#[cfg(feature = "zstd")]
+use zstd::stream::raw::{Decoder, InBuffer, Operation, OutBuffer};
+
+
+/// Transport compression of a shard's connections.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Compression {
+    #[default]
+    None,
+    /// A single zlib context spanning the whole connection.
+    ZlibStream,
+    /// A single zstd context spanning the whole connection.
+    ///
+    /// Requires the `zstd` feature; without it connections are uncompressed.
+    ZstdStream,
+}
+
+
+impl Compression {
+    /// Value of the gateway URL's `compress` parameter.
+    pub(crate) const fn query_value(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::ZlibStream => Some("zlib-stream"),
+            Self::ZstdStream if cfg!(feature = "zstd") => Some("zstd-stream"),
+            Self::ZstdStream => None,
+        }
+    }
+}
+
+
+/// Decompression context of a single connection.
+pub(crate) enum Decompressor {
+    Zlib(Inflater),
+    #[cfg(feature = "zstd")]
+    Zstd(ZstdDecoder),
+}
+
+
+impl Decompressor {
+    /// Context for a new connection, if its messages are compressed.
+    pub(crate) fn new(compression: Compression) -> Option<Self> {
+        match compression {
+            Compression::None => None,
+            Compression::ZlibStream => Some(Self::Zlib(Inflater::new())),
+            #[cfg(feature = "zstd")]
+            Compression::ZstdStream => ZstdDecoder::new().map(Self::Zstd).ok(),
+            #[cfg(not(feature = "zstd"))]
+            Compression::ZstdStream => None,
+        }
+    }
+
+    /// Feed a binary frame, returning the decompressed message once it is complete.
+    pub(crate) fn decompress(
+        &mut self,
+        frame: &[u8],
+    ) -> Result<Option<Vec<u8>>, Box<dyn Error + Send + Sync>> {
+        match self {
+            Self::Zlib(inflater) => Ok(inflater.inflate(frame)?),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(decoder) => Ok(Some(decoder.decode(frame)?)),
+        }
+    }
+}
+
+
+/// Inflates the binary frames of a `zlib-stream` connection.
+///
+/// Messages may be split over multiple frames; a message is complete once
+/// the buffered data ends with the zlib sync flush suffix.
+pub(crate) struct Inflater {
+    /// Compressed data of the message being received.
+    buffer: Vec<u8>,
+    decompress: Decompress,
+}
+
+
+impl Inflater {
+    const SUFFIX: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+    pub(crate) fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            decompress: Decompress::new(true),
+        }
+    }
+
+    /// Buffer a frame, returning the inflated message once it is complete.
+    pub(crate) fn inflate(&mut self, frame: &[u8]) -> Result<Option<Vec<u8>>, DecompressError> {
+        self.buffer.extend_from_slice(frame);
+
+        if !self.buffer.ends_with(&Self::SUFFIX) {
+            return Ok(None);
+        }
+
+        let start = self.decompress.total_in();
+        let mut output = Vec::with_capacity(self.buffer.len() * 4);
+
+        loop {
+            if output.len() == output.capacity() {
+                output.reserve(output.capacity().max(1024));
+            }
+
+            let consumed = (self.decompress.total_in() - start) as usize;
+            let produced = output.len();
+
+            self.decompress.decompress_vec(
+                &self.buffer[consumed..],
+                &mut output,
+                FlushDecompress::Sync,
+            )?;
+
+            let consumed_all = (self.decompress.total_in() - start) as usize == self.buffer.len();
+            let made_progress = (self.decompress.total_in() - start) as usize != consumed
+                || output.len() != produced;
+
+            // With input left or a full output buffer there may be more to inflate.
+            if (consumed_all && output.len() < output.capacity()) || !made_progress {
+                break;
+            }
+        }
+
+        self.buffer.clear();
+
+        Ok(Some(output))
+    }
+}
+
+
+/// Decodes the binary frames of a `zstd-stream` connection.
+///
+/// The gateway flushes the stream after every message, so each frame
+/// decodes into exactly one message.
+#[cfg(feature = "zstd")]
+pub(crate) struct ZstdDecoder {
+    decoder: Decoder<'static>,
+}
+
+
+#[cfg(feature = "zstd")]
+impl ZstdDecoder {
+    pub(crate) fn new() -> std::io::Result<Self> {
+        Ok(Self {
+            decoder: Decoder::new()?,
+        })
+    }
+
+    pub(crate) fn decode(&mut self, frame: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut input = InBuffer::around(frame);
+        let mut output = Vec::with_capacity(frame.len() * 4);
+
+        loop {
+            if output.len() == output.capacity() {
+                output.reserve(output.capacity().max(1024));
+            }
+
+            let position = output.len();
+            let mut buffer = OutBuffer::around_pos(&mut output, position);
+            self.decoder.run(&mut input, &mut buffer)?;
+            let filled = buffer.pos() == buffer.capacity();
+
+            // A full output buffer may hold back more of the message.
+            if input.pos() == frame.len() && !filled {
+                break;
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{Compress, Compression as Level, FlushCompress};
+
+    /// Compress messages on one zlib stream, sync flushing after each like the gateway.
+    fn compress(messages: &[&str]) -> Vec<Vec<u8>> {
+        let mut compress = Compress::new(Level::default(), true);
+
+        messages
+            .iter()
+            .map(|message| {
+                let mut frame = Vec::with_capacity(message.len() + 64);
+                compress
+                    .compress_vec(message.as_bytes(), &mut frame, FlushCompress::Sync)
+                    .unwrap();
+                assert!(frame.ends_with(&Inflater::SUFFIX));
+
+                frame
+            })
+            .collect()
+    }
+
+    #[test]
+    fn inflates_messages_of_one_stream() {
+        let messages = [r#"{"op":10}"#, r#"{"op":11}"#, r#"{"op":0,"t":"READY"}"#];
+        let mut inflater = Inflater::new();
+
+        for (frame, message) in compress(&messages).iter().zip(messages) {
+            assert_eq!(inflater.inflate(frame).unwrap().as_deref(), Some(message.as_bytes()));
+        }
+    }
+
+    #[test]
+    fn buffers_split_messages() {
+        let messages = [r#"{"op":10,"d":{"heartbeat_interval":41250}}"#, r#"{"op":11}"#];
+        let frames = compress(&messages);
+        let mut inflater = Inflater::new();
+
+        // Split within the sync flush suffix, so the first part doesn't end with it.
+        let (head, tail) = frames[0].split_at(frames[0].len() - 2);
+        assert_eq!(inflater.inflate(head).unwrap(), None);
+        assert_eq!(inflater.inflate(tail).unwrap().as_deref(), Some(messages[0].as_bytes()));
+
+        // The context carries over to the next message.
+        let (head, tail) = frames[1].split_at(1);
+        assert_eq!(inflater.inflate(head).unwrap(), None);
+        assert_eq!(inflater.inflate(tail).unwrap().as_deref(), Some(messages[1].as_bytes()));
+    }
+
+    #[test]
+    fn inflates_messages_larger_than_the_initial_buffer() {
+        let message = "a".repeat(64 * 1024);
+        let frames = compress(&[&message]);
+
+        let inflated = Inflater::new().inflate(&frames[0]).unwrap();
+        assert_eq!(inflated.as_deref(), Some(message.as_bytes()));
+    }
+}
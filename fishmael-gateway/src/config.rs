@@ -0,0 +1,362 @@
+use std::{
+    env,
+    sync::Arc,
+    time::Duration,
+    fmt::{Debug, Formatter, Result as FmtResult},
+};
+use twilight_model::gateway::{
+    payload::outgoing::{identify::IdentifyProperties, update_presence::UpdatePresencePayload},
+    Intents,
+    ShardId,
+};
+
+use crate::{
+    backoff::Backoff,
+    compression::Compression,
+    encoding::Encoding,
+    event_type::EventTypeFlags,
+    error::{ShardConfigError, ShardConfigErrorKind},
+    queue::{InMemoryQueue, Queue},
+    transport::{Connector, TungsteniteConnector},
+    Session,
+    Shard,
+};
+
+
+/// Default gateway URL, used unless the shard can resume elsewhere.
+pub const GATEWAY_URL: &str = "wss://gateway.discord.gg";
+
+/// Default gateway API version.
+pub const API_VERSION: u8 = 10;
+
+/// Gateway API versions this crate can speak.
+const SUPPORTED_API_VERSIONS: [u8; 2] = [9, 10];
+
+/// Bounds of the identify `large_threshold`.
+const LARGE_THRESHOLD_RANGE: std::ops::RangeInclusive<u64> = 50..=250;
+
+/// Default time allowed for the TCP, TLS and WebSocket handshakes.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default time allowed for the guilds announced by READY to arrive.
+const GUILD_LOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Default time allowed between connecting and HELLO.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default time allowed between identifying or resuming and READY or RESUMED.
+const READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+
+/// Validated identify and connection options shared by shards.
+///
+/// Create one through [`ShardBuilder`].
+#[derive(Clone)]
+pub struct ShardConfig {
+    api_version: u8,
+    backoff: Backoff,
+    compression: Compression,
+    connect_timeout: Duration,
+    connector: Arc<dyn Connector>,
+    encoding: Encoding,
+    event_types: EventTypeFlags,
+    gateway_url: String,
+    guild_load_timeout: Duration,
+    hello_timeout: Duration,
+    identify_properties: IdentifyProperties,
+    intents: Intents,
+    large_threshold: u64,
+    presence: Option<UpdatePresencePayload>,
+    queue: Arc<dyn Queue>,
+    ready_timeout: Duration,
+    token: String,
+}
+
+
+impl ShardConfig {
+    /// Default options for the given token and intents, not yet validated.
+    pub(crate) fn new(token: String, intents: Intents) -> Self {
+        Self {
+            api_version: API_VERSION,
+            backoff: Backoff::default(),
+            compression: Compression::None,
+            connect_timeout: CONNECT_TIMEOUT,
+            connector: Arc::new(TungsteniteConnector),
+            encoding: Encoding::Json,
+            event_types: EventTypeFlags::default(),
+            gateway_url: GATEWAY_URL.to_owned(),
+            guild_load_timeout: GUILD_LOAD_TIMEOUT,
+            hello_timeout: HELLO_TIMEOUT,
+            identify_properties: IdentifyProperties {
+                browser: "fishmael".to_owned(),
+                device: "fishmael".to_owned(),
+                os: env::consts::OS.to_owned(),
+            },
+            intents,
+            large_threshold: 250,
+            presence: None,
+            queue: Arc::new(InMemoryQueue::default()),
+            ready_timeout: READY_TIMEOUT,
+            token,
+        }
+    }
+
+    pub const fn api_version(&self) -> u8 {
+        self.api_version
+    }
+
+    pub const fn backoff(&self) -> Backoff {
+        self.backoff
+    }
+
+    pub const fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    pub const fn connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+
+    pub fn connector(&self) -> &dyn Connector {
+        &*self.connector
+    }
+
+    pub const fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub const fn event_types(&self) -> EventTypeFlags {
+        self.event_types
+    }
+
+    pub fn gateway_url(&self) -> &str {
+        &self.gateway_url
+    }
+
+    pub const fn guild_load_timeout(&self) -> Duration {
+        self.guild_load_timeout
+    }
+
+    pub const fn hello_timeout(&self) -> Duration {
+        self.hello_timeout
+    }
+
+    pub const fn identify_properties(&self) -> &IdentifyProperties {
+        &self.identify_properties
+    }
+
+    pub const fn intents(&self) -> Intents {
+        self.intents
+    }
+
+    pub const fn large_threshold(&self) -> u64 {
+        self.large_threshold
+    }
+
+    pub const fn presence(&self) -> Option<&UpdatePresencePayload> {
+        self.presence.as_ref()
+    }
+
+    pub fn queue(&self) -> &dyn Queue {
+        &*self.queue
+    }
+
+    pub const fn ready_timeout(&self) -> Duration {
+        self.ready_timeout
+    }
+
+    pub(crate) fn token(&self) -> &str {
+        &self.token
+    }
+
+    fn validate(&self) -> Result<(), ShardConfigError> {
+        let kind = if !SUPPORTED_API_VERSIONS.contains(&self.api_version) {
+            ShardConfigErrorKind::ApiVersion { version: self.api_version }
+        } else if !(self.gateway_url.starts_with("wss://") || self.gateway_url.starts_with("ws://"))
+            || self.gateway_url.contains('?')
+        {
+            ShardConfigErrorKind::GatewayUrl { url: self.gateway_url.clone() }
+        } else if !LARGE_THRESHOLD_RANGE.contains(&self.large_threshold) {
+            ShardConfigErrorKind::LargeThreshold { value: self.large_threshold }
+        } else if self.token.trim().is_empty() {
+            ShardConfigErrorKind::Token
+        } else {
+            return Ok(());
+        };
+
+        Err(ShardConfigError { kind })
+    }
+}
+
+
+impl Debug for ShardConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("ShardConfig")
+            .field("api_version", &self.api_version)
+            .field("backoff", &self.backoff)
+            .field("compression", &self.compression)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("connector", &"<connector>")
+            .field("encoding", &self.encoding)
+            .field("event_types", &self.event_types)
+            .field("gateway_url", &self.gateway_url)
+            .field("guild_load_timeout", &self.guild_load_timeout)
+            .field("hello_timeout", &self.hello_timeout)
+            .field("identify_properties", &self.identify_properties)
+            .field("intents", &self.intents)
+            .field("large_threshold", &self.large_threshold)
+            .field("presence", &self.presence)
+            .field("queue", &self.queue)
+            .field("ready_timeout", &self.ready_timeout)
+            .field("token", &"<redacted>")
+            .finish()
+    }
+}
+
+
+/// Builder for shards and their [`ShardConfig`].
+#[derive(Clone, Debug)]
+pub struct ShardBuilder {
+    config: ShardConfig,
+    /// Session to resume and its resume gateway URL.
+    session: Option<(Session, String)>,
+}
+
+
+impl ShardBuilder {
+    pub fn new(token: String, intents: Intents) -> Self {
+        Self {
+            config: ShardConfig::new(token, intents),
+            session: None,
+        }
+    }
+
+    /// Gateway API version to connect with.
+    pub const fn api_version(mut self, api_version: u8) -> Self {
+        self.config.api_version = api_version;
+        self
+    }
+
+    /// Delay policy for reconnecting after failed connection attempts.
+    pub const fn backoff(mut self, backoff: Backoff) -> Self {
+        self.config.backoff = backoff;
+        self
+    }
+
+    /// Compress the gateway's messages on the transport level.
+    pub const fn compression(mut self, compression: Compression) -> Self {
+        self.config.compression = compression;
+        self
+    }
+
+    /// Time allowed for the TCP, TLS and WebSocket handshakes of a connection.
+    pub const fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.config.connect_timeout = connect_timeout;
+        self
+    }
+
+    /// Open connections with this connector instead of tungstenite, e.g. to
+    /// go through a proxy or replay recorded traffic.
+    pub fn connector(mut self, connector: impl Connector + 'static) -> Self {
+        self.config.connector = Arc::new(connector);
+        self
+    }
+
+    /// Exchange payloads in the given encoding.
+    pub const fn encoding(mut self, encoding: Encoding) -> Self {
+        self.config.encoding = encoding;
+        self
+    }
+
+    /// Events to deserialize when polling for events; the others are skipped.
+    ///
+    /// The shard still handles the events it needs itself, such as READY,
+    /// whether or not they are included.
+    pub const fn event_types(mut self, event_types: EventTypeFlags) -> Self {
+        self.config.event_types = event_types;
+        self
+    }
+
+    /// Connect to this URL instead of Discord's gateway, e.g. a proxy.
+    pub fn gateway_url(mut self, gateway_url: String) -> Self {
+        self.config.gateway_url = gateway_url.trim_end_matches('/').to_owned();
+        self
+    }
+
+    /// Time allowed for the guilds announced by READY to arrive before the
+    /// shard counts as loaded anyway.
+    pub const fn guild_load_timeout(mut self, guild_load_timeout: Duration) -> Self {
+        self.config.guild_load_timeout = guild_load_timeout;
+        self
+    }
+
+    /// Time allowed for the gateway to say HELLO after connecting.
+    pub const fn hello_timeout(mut self, hello_timeout: Duration) -> Self {
+        self.config.hello_timeout = hello_timeout;
+        self
+    }
+
+    /// Properties identifying the connecting client.
+    pub fn identify_properties(mut self, identify_properties: IdentifyProperties) -> Self {
+        self.config.identify_properties = identify_properties;
+        self
+    }
+
+    /// Member count from which guilds are considered large, between 50 and 250.
+    pub const fn large_threshold(mut self, large_threshold: u64) -> Self {
+        self.config.large_threshold = large_threshold;
+        self
+    }
+
+    /// Presence to set when identifying.
+    pub fn presence(mut self, presence: UpdatePresencePayload) -> Self {
+        self.config.presence = Some(presence);
+        self
+    }
+
+    /// Queue to wait on before identifying, shared by all shards of the bot.
+    ///
+    /// Shards built from the same config share an [`InMemoryQueue`] with a
+    /// `max_concurrency` of 1 by default.
+    pub fn queue(mut self, queue: Arc<dyn Queue>) -> Self {
+        self.config.queue = queue;
+        self
+    }
+
+    /// Time allowed for READY or RESUMED after identifying or resuming.
+    pub const fn ready_timeout(mut self, ready_timeout: Duration) -> Self {
+        self.config.ready_timeout = ready_timeout;
+        self
+    }
+
+    /// Resume this session on the first connection instead of identifying.
+    ///
+    /// The session and URL are those returned by [`Shard::close`], e.g. saved
+    /// by a previous process. If the gateway no longer accepts the session,
+    /// the shard identifies anew.
+    pub fn session(mut self, session: Session, resume_gateway_url: String) -> Self {
+        self.session = Some((session, resume_gateway_url));
+        self
+    }
+
+    /// Validate the options and create a shard with them.
+    pub fn build(self, shard_id: ShardId) -> Result<Shard, ShardConfigError> {
+        let Self { config, session } = self;
+        config.validate()?;
+        let mut shard = Shard::with_config(shard_id, config);
+
+        if let Some((session, resume_gateway_url)) = session {
+            shard.resume(session, resume_gateway_url);
+        }
+
+        Ok(shard)
+    }
+
+    /// Validate the options for creating several shards.
+    ///
+    /// A [`session`](Self::session) belongs to a single shard and is not kept.
+    pub fn into_config(self) -> Result<ShardConfig, ShardConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
@@ -1,23 +1,69 @@
-use serde::de::DeserializeSeed;
 use twilight_model::gateway::event::{GatewayEvent, GatewayEventDeserializer};
 
-use crate::error::{ReceiveError, ReceiveErrorKind};
+use crate::{
+    encoding::Payload,
+    error::{ReceiveError, ReceiveErrorKind},
+    event_type::EventTypeFlags,
+};
 
-pub fn deserialize(event: String) -> Result<Option<GatewayEvent>, ReceiveError> {
-    let Some(gateway_deserializer) = GatewayEventDeserializer::from_json(&event) else {
+/// Deserialize an event, or skip it if it isn't one of `event_types`.
+///
+/// Events without a flag are always deserialized.
+pub fn deserialize(event: String, event_types: EventTypeFlags) -> Result<Option<GatewayEvent>, ReceiveError> {
+    deserialize_payload(Payload::Json(&event), event_types)
+}
+
+/// Deserialize an ETF encoded event, or skip it if it isn't one of `event_types`.
+pub fn deserialize_etf(event: &[u8], event_types: EventTypeFlags) -> Result<Option<GatewayEvent>, ReceiveError> {
+    deserialize_payload(Payload::Etf(event), event_types)
+}
+
+fn deserialize_payload(event: Payload<'_>, event_types: EventTypeFlags) -> Result<Option<GatewayEvent>, ReceiveError> {
+    let Some((op, _, event_type)) = event.parts() else {
         return Err(ReceiveError {
-            kind: ReceiveErrorKind::Deserializing { event },
+            kind: ReceiveErrorKind::Deserializing { event: event.to_string() },
             source: None,
         });
     };
 
-    let mut json_deserializer = serde_json::Deserializer::from_str(&event);
+    let flag = EventTypeFlags::from_parts(op, event_type.as_deref());
+    if flag.is_some_and(|flag| !event_types.contains(flag)) {
+        return Ok(None);
+    }
 
-    gateway_deserializer
-        .deserialize(&mut json_deserializer)
+    event
+        .deserialize_seed(GatewayEventDeserializer::new(op, event_type.as_deref()))
         .map(Some)
         .map_err(|source| ReceiveError {
-            kind: ReceiveErrorKind::Deserializing { event },
-            source: Some(Box::new(source)),
+            kind: ReceiveErrorKind::Deserializing { event: event.to_string() },
+            source: Some(source),
     })
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Payloads that would fail to deserialize as their event.
+    const TYPING_START: &str = r#"{"op":0,"s":2,"t":"TYPING_START","d":{"user_id":false}}"#;
+    const PRESENCE_UPDATE: &str = r#"{"op":0,"s":3,"t":"PRESENCE_UPDATE","d":{"user":null}}"#;
+
+    #[test]
+    fn skips_excluded_events_without_deserializing() {
+        let event_types = EventTypeFlags::all() - EventTypeFlags::TYPING_START - EventTypeFlags::PRESENCE_UPDATE;
+
+        for event in [TYPING_START, PRESENCE_UPDATE] {
+            assert!(deserialize(event.to_owned(), event_types).unwrap().is_none());
+            assert!(deserialize(event.to_owned(), EventTypeFlags::all()).is_err());
+        }
+    }
+
+    #[test]
+    fn deserializes_included_events() {
+        let event = r#"{"op":11,"d":null}"#.to_owned();
+
+        let event = deserialize(event, EventTypeFlags::GATEWAY_HEARTBEAT_ACK).unwrap();
+        assert!(matches!(event, Some(GatewayEvent::HeartbeatAck)));
+    }
+}
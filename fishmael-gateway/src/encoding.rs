@@ -0,0 +1,741 @@
+use serde::de::{
+    self,
+    value::SeqDeserializer,
+    Deserialize,
+    DeserializeOwned,
+    DeserializeSeed,
+    IgnoredAny,
+    IntoDeserializer,
+    Visitor,
+};
+use serde_json::{Map, Number, Value};
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+use twilight_model::gateway::event::GatewayEventDeserializer;
+
+
+/// Payload encoding of a shard's connections.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Json,
+    /// Erlang external term format, sent as binary frames.
+    ///
+    /// Payloads are deserialized straight from their terms, so snowflakes
+    /// are read as native integers instead of parsed from strings.
+    Etf,
+}
+
+
+impl Encoding {
+    /// Value of the gateway URL's `encoding` parameter.
+    pub(crate) const fn query_value(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Etf => "etf",
+        }
+    }
+}
+
+
+#[derive(Debug)]
+pub(crate) struct EtfError(String);
+
+
+impl Display for EtfError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for EtfError {}
+
+impl de::Error for EtfError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+
+const VERSION: u8 = 131;
+
+const NEW_FLOAT_EXT: u8 = 70;
+const SMALL_INTEGER_EXT: u8 = 97;
+const INTEGER_EXT: u8 = 98;
+const FLOAT_EXT: u8 = 99;
+const ATOM_EXT: u8 = 100;
+const SMALL_TUPLE_EXT: u8 = 104;
+const LARGE_TUPLE_EXT: u8 = 105;
+const NIL_EXT: u8 = 106;
+const STRING_EXT: u8 = 107;
+const LIST_EXT: u8 = 108;
+const BINARY_EXT: u8 = 109;
+const SMALL_BIG_EXT: u8 = 110;
+const LARGE_BIG_EXT: u8 = 111;
+const SMALL_ATOM_EXT: u8 = 115;
+const MAP_EXT: u8 = 116;
+const ATOM_UTF8_EXT: u8 = 118;
+const SMALL_ATOM_UTF8_EXT: u8 = 119;
+
+
+/// Received gateway payload in either encoding.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Payload<'a> {
+    Json(&'a str),
+    Etf(&'a [u8]),
+}
+
+
+impl<'a> Payload<'a> {
+    /// Opcode, sequence and event type, read without deserializing `d`.
+    pub(crate) fn parts(self) -> Option<(u8, Option<u64>, Option<Cow<'a, str>>)> {
+        match self {
+            Self::Json(json) => GatewayEventDeserializer::from_json(json).map(GatewayEventDeserializer::into_parts),
+            Self::Etf(etf) => {
+                let (op, sequence, event_type) = parts(etf).ok()?;
+                Some((op, sequence, event_type.map(Cow::Borrowed)))
+            },
+        }
+    }
+
+    pub(crate) fn deserialize<T: DeserializeOwned>(self) -> Result<T, Box<dyn Error + Send + Sync>> {
+        self.deserialize_seed(std::marker::PhantomData)
+    }
+
+    pub(crate) fn deserialize_seed<T: DeserializeSeed<'a>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Box<dyn Error + Send + Sync>> {
+        match self {
+            Self::Json(json) => {
+                let mut deserializer = serde_json::Deserializer::from_str(json);
+                let value = seed.deserialize(&mut deserializer)?;
+                deserializer.end()?;
+
+                Ok(value)
+            },
+            Self::Etf(etf) => {
+                let mut deserializer = Deserializer::new(etf)?;
+                let value = seed.deserialize(&mut deserializer)?;
+                deserializer.end()?;
+
+                Ok(value)
+            },
+        }
+    }
+}
+
+
+impl Display for Payload<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Json(json) => f.write_str(json),
+            Self::Etf(etf) => write!(f, "{etf:?}"),
+        }
+    }
+}
+
+
+/// Read the opcode, sequence and event type of an ETF payload.
+///
+/// Unlike the JSON scan, this doesn't depend on the order of the fields.
+fn parts(etf: &[u8]) -> Result<(u8, Option<u64>, Option<&str>), EtfError> {
+    let mut deserializer = Deserializer::new(etf)?;
+    if deserializer.u8()? != MAP_EXT {
+        return Err(EtfError("payload is not a map".into()));
+    }
+
+    let (mut op, mut sequence, mut event_type) = (None, None, None);
+
+    for _ in 0..deserializer.u32()? {
+        match deserializer.str()? {
+            "op" => op = Some(Deserialize::deserialize(&mut deserializer)?),
+            "s" => sequence = Deserialize::deserialize(&mut deserializer)?,
+            "t" => event_type = Deserialize::deserialize(&mut deserializer)?,
+            _ => {
+                IgnoredAny::deserialize(&mut deserializer)?;
+            },
+        }
+    }
+
+    let op = op.ok_or_else(|| EtfError("missing opcode".into()))?;
+    Ok((op, sequence, event_type))
+}
+
+
+/// Encode a JSON payload as ETF.
+///
+/// Strings are encoded as binaries, `null` and booleans as atoms.
+pub(crate) fn encode(json: &str) -> Result<Vec<u8>, EtfError> {
+    let value = serde_json::from_str::<Value>(json)
+        .map_err(|source| EtfError(format!("invalid JSON payload: {source}")))?;
+
+    let mut etf = vec![VERSION];
+    encode_value(&value, &mut etf)?;
+
+    Ok(etf)
+}
+
+
+/// Deserializes a value from the terms of an ETF payload.
+///
+/// Integers, snowflakes included, are visited as integers, binaries as
+/// borrowed strings, and the atoms `nil`, `true` and `false` as their
+/// values; other atoms are visited as strings.
+pub(crate) struct Deserializer<'de> {
+    etf: &'de [u8],
+    position: usize,
+}
+
+
+impl<'de> Deserializer<'de> {
+    pub(crate) fn new(etf: &'de [u8]) -> Result<Self, EtfError> {
+        let mut deserializer = Self { etf, position: 0 };
+
+        if deserializer.u8()? != VERSION {
+            return Err(EtfError("unsupported ETF version".into()));
+        }
+
+        Ok(deserializer)
+    }
+
+    /// Fail if anything follows the deserialized term.
+    pub(crate) fn end(&self) -> Result<(), EtfError> {
+        if self.position != self.etf.len() {
+            return Err(EtfError("trailing bytes after term".into()));
+        }
+
+        Ok(())
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'de [u8], EtfError> {
+        let bytes = self.position
+            .checked_add(len)
+            .and_then(|end| self.etf.get(self.position..end))
+            .ok_or_else(|| EtfError("unexpected end of term".into()))?;
+
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn peek(&self) -> Result<u8, EtfError> {
+        self.etf
+            .get(self.position)
+            .copied()
+            .ok_or_else(|| EtfError("unexpected end of term".into()))
+    }
+
+    fn u8(&mut self) -> Result<u8, EtfError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<usize, EtfError> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()).into())
+    }
+
+    fn u32(&mut self) -> Result<usize, EtfError> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()) as usize)
+    }
+
+    fn text(&mut self, len: usize) -> Result<&'de str, EtfError> {
+        std::str::from_utf8(self.bytes(len)?)
+            .map_err(|_| EtfError("text is not valid UTF-8".into()))
+    }
+
+    /// Read an atom or binary, such as a map key.
+    fn str(&mut self) -> Result<&'de str, EtfError> {
+        match self.u8()? {
+            ATOM_EXT | ATOM_UTF8_EXT => {
+                let len = self.u16()?;
+                self.text(len)
+            },
+            SMALL_ATOM_EXT | SMALL_ATOM_UTF8_EXT => {
+                let len = self.u8()?.into();
+                self.text(len)
+            },
+            BINARY_EXT => {
+                let len = self.u32()?;
+                self.text(len)
+            },
+            tag => Err(EtfError(format!("expected an atom or binary, found tag {tag}"))),
+        }
+    }
+
+    fn atom<V: Visitor<'de>>(atom: &'de str, visitor: V) -> Result<V::Value, EtfError> {
+        match atom {
+            "nil" | "null" => visitor.visit_unit(),
+            "true" => visitor.visit_bool(true),
+            "false" => visitor.visit_bool(false),
+            _ => visitor.visit_borrowed_str(atom),
+        }
+    }
+
+    fn big<V: Visitor<'de>>(&mut self, len: usize, visitor: V) -> Result<V::Value, EtfError> {
+        let negative = self.u8()? != 0;
+        let digits = self.bytes(len)?;
+
+        if len > 8 {
+            return Err(EtfError("integer does not fit in 64 bits".into()));
+        }
+
+        let magnitude = digits
+            .iter()
+            .rev()
+            .fold(0u64, |value, digit| (value << 8) | u64::from(*digit));
+
+        if negative {
+            let value = 0i64
+                .checked_sub_unsigned(magnitude)
+                .ok_or_else(|| EtfError("integer does not fit in 64 bits".into()))?;
+            visitor.visit_i64(value)
+        } else {
+            visitor.visit_u64(magnitude)
+        }
+    }
+
+    fn seq<V: Visitor<'de>>(&mut self, len: usize, visitor: V) -> Result<V::Value, EtfError> {
+        let mut access = Access { deserializer: self, remaining: len };
+        let value = visitor.visit_seq(&mut access)?;
+
+        if access.remaining != 0 {
+            return Err(EtfError("list has more elements than expected".into()));
+        }
+
+        Ok(value)
+    }
+}
+
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = EtfError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EtfError> {
+        match self.u8()? {
+            SMALL_INTEGER_EXT => visitor.visit_u8(self.u8()?),
+            INTEGER_EXT => visitor.visit_i32(i32::from_be_bytes(self.bytes(4)?.try_into().unwrap())),
+            NEW_FLOAT_EXT => visitor.visit_f64(f64::from_be_bytes(self.bytes(8)?.try_into().unwrap())),
+            FLOAT_EXT => {
+                let value = self.text(31)?
+                    .trim_end_matches('\0')
+                    .parse::<f64>()
+                    .map_err(|_| EtfError("invalid float".into()))?;
+                visitor.visit_f64(value)
+            },
+            SMALL_BIG_EXT => {
+                let len = self.u8()?.into();
+                self.big(len, visitor)
+            },
+            LARGE_BIG_EXT => {
+                let len = self.u32()?;
+                self.big(len, visitor)
+            },
+            ATOM_EXT | ATOM_UTF8_EXT => {
+                let len = self.u16()?;
+                Deserializer::atom(self.text(len)?, visitor)
+            },
+            SMALL_ATOM_EXT | SMALL_ATOM_UTF8_EXT => {
+                let len = self.u8()?.into();
+                Deserializer::atom(self.text(len)?, visitor)
+            },
+            BINARY_EXT => {
+                let len = self.u32()?;
+                visitor.visit_borrowed_str(self.text(len)?)
+            },
+            STRING_EXT => {
+                // Lists of small integers, which Erlang packs as bytes.
+                let len = self.u16()?;
+                let bytes = self.bytes(len)?;
+                visitor.visit_seq(SeqDeserializer::new(bytes.iter().copied()))
+            },
+            NIL_EXT => self.seq(0, visitor),
+            LIST_EXT => {
+                let len = self.u32()?;
+                let value = self.seq(len, visitor)?;
+
+                // Proper lists end with an empty list as their tail.
+                match self.u8()? {
+                    NIL_EXT => Ok(value),
+                    _ => Err(EtfError("improper lists are not supported".into())),
+                }
+            },
+            SMALL_TUPLE_EXT => {
+                let len = self.u8()?.into();
+                self.seq(len, visitor)
+            },
+            LARGE_TUPLE_EXT => {
+                let len = self.u32()?;
+                self.seq(len, visitor)
+            },
+            MAP_EXT => {
+                let len = self.u32()?;
+                let mut access = Access { deserializer: self, remaining: len };
+                let value = visitor.visit_map(&mut access)?;
+
+                if access.remaining != 0 {
+                    return Err(EtfError("map has more entries than expected".into()));
+                }
+
+                Ok(value)
+            },
+            tag => Err(EtfError(format!("unsupported ETF tag {tag}"))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EtfError> {
+        let is_nil = match self.peek()? {
+            SMALL_ATOM_EXT | SMALL_ATOM_UTF8_EXT => {
+                matches!(self.etf.get(self.position + 1..self.position + 5), Some([3, b'n', b'i', b'l']))
+            },
+            ATOM_EXT | ATOM_UTF8_EXT => {
+                matches!(self.etf.get(self.position + 1..self.position + 6), Some([0, 3, b'n', b'i', b'l']))
+            },
+            _ => false,
+        };
+
+        if is_nil {
+            IgnoredAny::deserialize(&mut *self)?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, EtfError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, EtfError> {
+        // Only unit variants, which are named by a string.
+        visitor.visit_enum(self.str()?.into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+
+/// Elements of a list or tuple, or entries of a map.
+struct Access<'a, 'de> {
+    deserializer: &'a mut Deserializer<'de>,
+    remaining: usize,
+}
+
+
+impl<'de> de::SeqAccess<'de> for Access<'_, 'de> {
+    type Error = EtfError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, EtfError> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.deserializer).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+
+impl<'de> de::MapAccess<'de> for Access<'_, 'de> {
+    type Error = EtfError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, EtfError> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+        seed.deserialize(MapKey(&mut *self.deserializer)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, EtfError> {
+        seed.deserialize(&mut *self.deserializer)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+
+/// Deserializes map keys, which JSON-shaped types expect to be strings.
+struct MapKey<'a, 'de>(&'a mut Deserializer<'de>);
+
+
+impl<'de> de::Deserializer<'de> for MapKey<'_, 'de> {
+    type Error = EtfError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EtfError> {
+        match self.0.peek()? {
+            ATOM_EXT | ATOM_UTF8_EXT | SMALL_ATOM_EXT | SMALL_ATOM_UTF8_EXT => {
+                visitor.visit_borrowed_str(self.0.str()?)
+            },
+            _ => self.0.deserialize_any(visitor),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+
+fn encode_value(value: &Value, out: &mut Vec<u8>) -> Result<(), EtfError> {
+    match value {
+        Value::Null => encode_atom("nil", out),
+        Value::Bool(value) => encode_atom(if *value { "true" } else { "false" }, out),
+        Value::Number(number) => encode_number(number, out)?,
+        Value::String(string) => encode_binary(string, out)?,
+        Value::Array(values) => {
+            if values.is_empty() {
+                out.push(NIL_EXT);
+                return Ok(());
+            }
+
+            out.push(LIST_EXT);
+            out.extend_from_slice(&length(values.len())?.to_be_bytes());
+            for value in values {
+                encode_value(value, out)?;
+            }
+            out.push(NIL_EXT);
+        },
+        Value::Object(map) => encode_map(map, out)?,
+    }
+
+    Ok(())
+}
+
+
+fn encode_map(map: &Map<String, Value>, out: &mut Vec<u8>) -> Result<(), EtfError> {
+    out.push(MAP_EXT);
+    out.extend_from_slice(&length(map.len())?.to_be_bytes());
+
+    for (key, value) in map {
+        encode_binary(key, out)?;
+        encode_value(value, out)?;
+    }
+
+    Ok(())
+}
+
+
+fn encode_atom(atom: &str, out: &mut Vec<u8>) {
+    // Only used for the short atoms above.
+    out.push(SMALL_ATOM_UTF8_EXT);
+    out.push(atom.len() as u8);
+    out.extend_from_slice(atom.as_bytes());
+}
+
+
+fn encode_binary(binary: &str, out: &mut Vec<u8>) -> Result<(), EtfError> {
+    out.push(BINARY_EXT);
+    out.extend_from_slice(&length(binary.len())?.to_be_bytes());
+    out.extend_from_slice(binary.as_bytes());
+
+    Ok(())
+}
+
+
+fn encode_number(number: &Number, out: &mut Vec<u8>) -> Result<(), EtfError> {
+    if let Some(value) = number.as_u64().and_then(|value| u8::try_from(value).ok()) {
+        out.push(SMALL_INTEGER_EXT);
+        out.push(value);
+    } else if let Some(value) = number.as_i64().and_then(|value| i32::try_from(value).ok()) {
+        out.push(INTEGER_EXT);
+        out.extend_from_slice(&value.to_be_bytes());
+    } else if let Some(value) = number.as_i64() {
+        encode_big(value < 0, value.unsigned_abs(), out);
+    } else if let Some(value) = number.as_u64() {
+        encode_big(false, value, out);
+    } else {
+        let value = number.as_f64().ok_or_else(|| EtfError("unsupported number".into()))?;
+        out.push(NEW_FLOAT_EXT);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+
+    Ok(())
+}
+
+
+fn encode_big(negative: bool, magnitude: u64, out: &mut Vec<u8>) {
+    let digits = magnitude.to_le_bytes();
+    let len = digits.iter().rposition(|digit| *digit != 0).map_or(0, |i| i + 1);
+
+    out.push(SMALL_BIG_EXT);
+    out.push(len as u8);
+    out.push(u8::from(negative));
+    out.extend_from_slice(&digits[..len]);
+}
+
+
+fn length(len: usize) -> Result<u32, EtfError> {
+    u32::try_from(len).map_err(|_| EtfError("term is too large to encode".into()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{deserialize::deserialize_etf, event_type::EventTypeFlags};
+    use twilight_model::gateway::event::{DispatchEvent, GatewayEvent};
+
+    fn deserialize<T: DeserializeOwned>(etf: &[u8]) -> Result<T, Box<dyn Error + Send + Sync>> {
+        Payload::Etf(etf).deserialize()
+    }
+
+    fn round_trip(json: &str) -> Value {
+        deserialize(&encode(json).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trips_values() {
+        let values = [
+            "null",
+            "true",
+            "false",
+            "0",
+            "255",
+            "256",
+            "-1",
+            "2147483647",
+            "-2147483649",
+            "1234567890123456789",
+            "18446744073709551615",
+            "-9223372036854775808",
+            "1.5",
+            r#""""#,
+            r#""fishmael 🐟""#,
+            "[]",
+            r#"[1,"two",[3],{"four":null}]"#,
+            "{}",
+            r#"{"a":{"b":[true,false]},"c":-3.25}"#,
+        ];
+
+        for json in values {
+            assert_eq!(round_trip(json), serde_json::from_str::<Value>(json).unwrap(), "{json}");
+        }
+    }
+
+    #[test]
+    fn deserializes_gateway_events() {
+        // The payload comes first in the encoded map, which ETF doesn't mind.
+        let etf = encode(r#"{"d":{"heartbeat_interval":41250},"op":10,"s":null,"t":null}"#).unwrap();
+        assert_eq!(Payload::Etf(&etf).parts(), Some((10, None, None)));
+
+        let event = deserialize_etf(&etf, EventTypeFlags::all()).unwrap();
+        assert!(matches!(event, Some(GatewayEvent::Hello(hello)) if hello.heartbeat_interval == 41250));
+    }
+
+    #[test]
+    fn deserializes_snowflakes_as_integers() {
+        let mut etf = vec![VERSION, MAP_EXT, 0, 0, 0, 4];
+        etf.extend_from_slice(&[SMALL_ATOM_UTF8_EXT, 1, b'd', MAP_EXT, 0, 0, 0, 3]);
+        etf.extend_from_slice(&[SMALL_ATOM_UTF8_EXT, 2, b'i', b'd']);
+        encode_big(false, 1_234_567_890_123_456_789, &mut etf);
+        etf.extend_from_slice(&[SMALL_ATOM_UTF8_EXT, 11]);
+        etf.extend_from_slice(b"unavailable");
+        etf.extend_from_slice(&[SMALL_ATOM_UTF8_EXT, 4, b't', b'r', b'u', b'e']);
+        etf.extend_from_slice(&[SMALL_ATOM_UTF8_EXT, 4]);
+        etf.extend_from_slice(b"name");
+        etf.extend_from_slice(&[SMALL_ATOM_UTF8_EXT, 3, b'n', b'i', b'l']);
+        etf.extend_from_slice(&[SMALL_ATOM_UTF8_EXT, 2, b'o', b'p', SMALL_INTEGER_EXT, 0]);
+        etf.extend_from_slice(&[SMALL_ATOM_UTF8_EXT, 1, b's', SMALL_INTEGER_EXT, 7]);
+        etf.extend_from_slice(&[SMALL_ATOM_UTF8_EXT, 1, b't', SMALL_ATOM_UTF8_EXT, 12]);
+        etf.extend_from_slice(b"GUILD_DELETE");
+
+        assert_eq!(Payload::Etf(&etf).parts(), Some((0, Some(7), Some("GUILD_DELETE".into()))));
+
+        let Some(GatewayEvent::Dispatch(7, DispatchEvent::GuildDelete(guild))) =
+            deserialize_etf(&etf, EventTypeFlags::all()).unwrap()
+        else {
+            panic!("payload should be a GUILD_DELETE");
+        };
+        assert_eq!(guild.id.get(), 1_234_567_890_123_456_789);
+        assert!(guild.unavailable);
+
+        // Excluded events are skipped after reading only the opcode and type.
+        assert!(deserialize_etf(&etf, EventTypeFlags::GUILD_CREATE).unwrap().is_none());
+    }
+
+    #[test]
+    fn deserializes_erlang_terms() {
+        let etf = [
+            VERSION,
+            MAP_EXT, 0, 0, 0, 3,
+            // nil => nil, where only the value is null
+            ATOM_EXT, 0, 3, b'n', b'i', b'l',
+            ATOM_EXT, 0, 3, b'n', b'i', b'l',
+            // atom => "ab"
+            SMALL_ATOM_EXT, 4, b'a', b't', b'o', b'm',
+            STRING_EXT, 0, 2, b'a', b'b',
+            // tuple => {1, []}
+            BINARY_EXT, 0, 0, 0, 5, b't', b'u', b'p', b'l', b'e',
+            SMALL_TUPLE_EXT, 2, SMALL_INTEGER_EXT, 1, NIL_EXT,
+        ];
+
+        let value = deserialize::<Value>(&etf).unwrap();
+        assert_eq!(value, serde_json::json!({"nil": null, "atom": [97, 98], "tuple": [1, []]}));
+
+        let optional = deserialize::<Option<u8>>(&[VERSION, SMALL_ATOM_UTF8_EXT, 3, b'n', b'i', b'l']);
+        assert_eq!(optional.unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_malformed_terms() {
+        let terms: [&[u8]; 10] = [
+            &[],
+            &[130, NIL_EXT],
+            &[VERSION],
+            &[VERSION, 0],
+            &[VERSION, BINARY_EXT, 0, 0, 0, 4, b'a'],
+            &[VERSION, BINARY_EXT, 0, 0, 0, 1, 0xff],
+            &[VERSION, LIST_EXT, 0, 0, 0, 1, SMALL_INTEGER_EXT, 1, SMALL_INTEGER_EXT, 2],
+            &[VERSION, SMALL_BIG_EXT, 9, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1],
+            &[VERSION, MAP_EXT, 0, 0, 0, 1, SMALL_INTEGER_EXT, 1],
+            &[VERSION, NIL_EXT, NIL_EXT],
+        ];
+
+        for etf in terms {
+            assert!(deserialize::<Value>(etf).is_err(), "{etf:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_overflowing_negative_integers() {
+        let etf = [VERSION, SMALL_BIG_EXT, 8, 1, 0, 0, 0, 0, 0, 0, 0, 0x80 | 1];
+        assert!(deserialize::<Value>(&etf).is_err());
+    }
+
+    #[test]
+    fn rejects_payloads_without_opcode() {
+        let etf = encode(r#"{"d":null,"t":"READY"}"#).unwrap();
+
+        assert!(Payload::Etf(&etf).parts().is_none());
+        assert!(deserialize_etf(&etf, EventTypeFlags::all()).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_json() {
+        assert!(encode("{").is_err());
+    }
+}
@@ -3,9 +3,18 @@ use std::fmt::{Display, Formatter, Result as FmtResult};
 
 #[derive(Debug)]
 pub enum ReceiveErrorKind {
+    /// The connection could not be established in time.
+    ConnectTimeout,
+    Decompressing,
+    /// A binary payload of a JSON connection is not valid UTF-8.
+    Decoding,
     Deserializing{
         event: String,
     },
+    /// The gateway did not say HELLO in time.
+    HelloTimeout,
+    /// The gateway did not answer the identify or resume in time.
+    ReadyTimeout,
     Reconnect,
 }
 
@@ -17,13 +26,25 @@ pub struct ReceiveError {
 }
 
 
+impl ReceiveError {
+    pub const fn kind(&self) -> &ReceiveErrorKind {
+        &self.kind
+    }
+}
+
+
 impl Display for ReceiveError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match &self.kind {
+            ReceiveErrorKind::ConnectTimeout => f.write_str("timed out connecting to the gateway"),
+            ReceiveErrorKind::Decompressing => f.write_str("failed to decompress message"),
+            ReceiveErrorKind::Decoding => f.write_str("failed to decode message"),
             ReceiveErrorKind::Deserializing { event } => {
                 f.write_str("failed to deserialize event: ")?;
                 f.write_str(event)
             },
+            ReceiveErrorKind::HelloTimeout => f.write_str("timed out waiting for hello"),
+            ReceiveErrorKind::ReadyTimeout => f.write_str("timed out waiting for ready or resumed"),
             ReceiveErrorKind::Reconnect => f.write_str("failed to reconnect")
         }
     }
@@ -36,3 +57,177 @@ impl std::error::Error for ReceiveError {
             .map(|source| &**source as &(dyn std::error::Error + 'static))
     }
 }
+
+
+#[derive(Debug)]
+pub enum SendErrorKind {
+    /// The shard was dropped.
+    Closed,
+    Serializing,
+}
+
+
+#[derive(Debug)]
+pub struct SendError {
+    pub(crate) kind: SendErrorKind,
+    pub(crate) source: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+
+impl Display for SendError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            SendErrorKind::Closed => f.write_str("shard was dropped"),
+            SendErrorKind::Serializing => f.write_str("failed to serialize command"),
+        }
+    }
+}
+
+impl std::error::Error for SendError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn std::error::Error + 'static))
+    }
+}
+
+
+#[derive(Debug)]
+pub enum MemberRequestErrorKind {
+    /// The request could not be queued.
+    Sending,
+    /// A chunk of the response did not arrive in time.
+    TimedOut,
+}
+
+
+#[derive(Debug)]
+pub struct MemberRequestError {
+    pub(crate) kind: MemberRequestErrorKind,
+    pub(crate) source: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+
+impl MemberRequestError {
+    pub const fn kind(&self) -> &MemberRequestErrorKind {
+        &self.kind
+    }
+}
+
+
+impl Display for MemberRequestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            MemberRequestErrorKind::Sending => f.write_str("failed to send member request"),
+            MemberRequestErrorKind::TimedOut => f.write_str("timed out waiting for member chunks"),
+        }
+    }
+}
+
+impl std::error::Error for MemberRequestError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn std::error::Error + 'static))
+    }
+}
+
+
+#[derive(Debug)]
+pub enum ShardConfigErrorKind {
+    /// The API version isn't supported.
+    ApiVersion {
+        version: u8,
+    },
+    /// The gateway URL isn't a bare websocket URL.
+    GatewayUrl {
+        url: String,
+    },
+    /// The large threshold is outside of 50..=250.
+    LargeThreshold {
+        value: u64,
+    },
+    /// The token is empty.
+    Token,
+}
+
+
+#[derive(Debug)]
+pub struct ShardConfigError {
+    pub(crate) kind: ShardConfigErrorKind,
+}
+
+
+impl ShardConfigError {
+    pub const fn kind(&self) -> &ShardConfigErrorKind {
+        &self.kind
+    }
+}
+
+
+impl Display for ShardConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            ShardConfigErrorKind::ApiVersion { version } => {
+                write!(f, "unsupported gateway API version {version}")
+            },
+            ShardConfigErrorKind::GatewayUrl { url } => {
+                write!(f, "gateway URL {url} is not a websocket URL without query")
+            },
+            ShardConfigErrorKind::LargeThreshold { value } => {
+                write!(f, "large threshold {value} is not between 50 and 250")
+            },
+            ShardConfigErrorKind::Token => f.write_str("token is empty"),
+        }
+    }
+}
+
+impl std::error::Error for ShardConfigError {}
+
+
+#[derive(Debug)]
+pub enum GatewayInfoErrorKind {
+    /// The response could not be deserialized.
+    Deserializing,
+    /// The request could not be sent.
+    Requesting,
+    /// The API responded with an unsuccessful status.
+    Status {
+        status: u16,
+    },
+}
+
+
+#[derive(Debug)]
+pub struct GatewayInfoError {
+    pub(crate) kind: GatewayInfoErrorKind,
+    pub(crate) source: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+
+impl GatewayInfoError {
+    pub const fn kind(&self) -> &GatewayInfoErrorKind {
+        &self.kind
+    }
+}
+
+
+impl Display for GatewayInfoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            GatewayInfoErrorKind::Deserializing => f.write_str("failed to deserialize gateway info"),
+            GatewayInfoErrorKind::Requesting => f.write_str("failed to request gateway info"),
+            GatewayInfoErrorKind::Status { status } => {
+                write!(f, "gateway info request failed with status {status}")
+            },
+        }
+    }
+}
+
+impl std::error::Error for GatewayInfoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn std::error::Error + 'static))
+    }
+}
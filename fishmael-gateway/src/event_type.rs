@@ -0,0 +1,169 @@
+use bitflags::bitflags;
+use twilight_model::gateway::OpCode;
+
+
+bitflags! {
+    /// Set of gateway events, by their `t` field or opcode.
+    ///
+    /// Unwanted events are skipped before they are deserialized.
+    #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+    pub struct EventTypeFlags: u128 {
+        const APPLICATION_COMMAND_PERMISSIONS_UPDATE = 1 << 0;
+        const AUTO_MODERATION_ACTION_EXECUTION = 1 << 1;
+        const AUTO_MODERATION_RULE_CREATE = 1 << 2;
+        const AUTO_MODERATION_RULE_DELETE = 1 << 3;
+        const AUTO_MODERATION_RULE_UPDATE = 1 << 4;
+        const CHANNEL_CREATE = 1 << 5;
+        const CHANNEL_DELETE = 1 << 6;
+        const CHANNEL_PINS_UPDATE = 1 << 7;
+        const CHANNEL_UPDATE = 1 << 8;
+        const GATEWAY_HEARTBEAT = 1 << 9;
+        const GATEWAY_HEARTBEAT_ACK = 1 << 10;
+        const GATEWAY_HELLO = 1 << 11;
+        const GATEWAY_INVALIDATE_SESSION = 1 << 12;
+        const GATEWAY_RECONNECT = 1 << 13;
+        const GIFT_CODE_UPDATE = 1 << 14;
+        const GUILD_AUDIT_LOG_ENTRY_CREATE = 1 << 15;
+        const GUILD_BAN_ADD = 1 << 16;
+        const GUILD_BAN_REMOVE = 1 << 17;
+        const GUILD_CREATE = 1 << 18;
+        const GUILD_DELETE = 1 << 19;
+        const GUILD_EMOJIS_UPDATE = 1 << 20;
+        const GUILD_INTEGRATIONS_UPDATE = 1 << 21;
+        const GUILD_MEMBERS_CHUNK = 1 << 22;
+        const GUILD_MEMBER_ADD = 1 << 23;
+        const GUILD_MEMBER_REMOVE = 1 << 24;
+        const GUILD_MEMBER_UPDATE = 1 << 25;
+        const GUILD_ROLE_CREATE = 1 << 26;
+        const GUILD_ROLE_DELETE = 1 << 27;
+        const GUILD_ROLE_UPDATE = 1 << 28;
+        const GUILD_SCHEDULED_EVENT_CREATE = 1 << 29;
+        const GUILD_SCHEDULED_EVENT_DELETE = 1 << 30;
+        const GUILD_SCHEDULED_EVENT_UPDATE = 1 << 31;
+        const GUILD_SCHEDULED_EVENT_USER_ADD = 1 << 32;
+        const GUILD_SCHEDULED_EVENT_USER_REMOVE = 1 << 33;
+        const GUILD_STICKERS_UPDATE = 1 << 34;
+        const GUILD_UPDATE = 1 << 35;
+        const INTEGRATION_CREATE = 1 << 36;
+        const INTEGRATION_DELETE = 1 << 37;
+        const INTEGRATION_UPDATE = 1 << 38;
+        const INTERACTION_CREATE = 1 << 39;
+        const INVITE_CREATE = 1 << 40;
+        const INVITE_DELETE = 1 << 41;
+        const MESSAGE_CREATE = 1 << 42;
+        const MESSAGE_DELETE = 1 << 43;
+        const MESSAGE_DELETE_BULK = 1 << 44;
+        const MESSAGE_REACTION_ADD = 1 << 45;
+        const MESSAGE_REACTION_REMOVE = 1 << 46;
+        const MESSAGE_REACTION_REMOVE_ALL = 1 << 47;
+        const MESSAGE_REACTION_REMOVE_EMOJI = 1 << 48;
+        const MESSAGE_UPDATE = 1 << 49;
+        const PRESENCES_REPLACE = 1 << 50;
+        const PRESENCE_UPDATE = 1 << 51;
+        const READY = 1 << 52;
+        const RESUMED = 1 << 53;
+        const STAGE_INSTANCE_CREATE = 1 << 54;
+        const STAGE_INSTANCE_DELETE = 1 << 55;
+        const STAGE_INSTANCE_UPDATE = 1 << 56;
+        const THREAD_CREATE = 1 << 57;
+        const THREAD_DELETE = 1 << 58;
+        const THREAD_LIST_SYNC = 1 << 59;
+        const THREAD_MEMBERS_UPDATE = 1 << 60;
+        const THREAD_MEMBER_UPDATE = 1 << 61;
+        const THREAD_UPDATE = 1 << 62;
+        const TYPING_START = 1 << 63;
+        const UNAVAILABLE_GUILD = 1 << 64;
+        const USER_UPDATE = 1 << 65;
+        const VOICE_SERVER_UPDATE = 1 << 66;
+        const VOICE_STATE_UPDATE = 1 << 67;
+        const WEBHOOKS_UPDATE = 1 << 68;
+    }
+}
+
+
+impl EventTypeFlags {
+    /// Flag of a gateway event, if it is known.
+    pub(crate) fn from_parts(op: u8, event_type: Option<&str>) -> Option<Self> {
+        let flag = match (OpCode::from(op)?, event_type) {
+            (OpCode::Dispatch, Some("APPLICATION_COMMAND_PERMISSIONS_UPDATE")) => Self::APPLICATION_COMMAND_PERMISSIONS_UPDATE,
+            (OpCode::Dispatch, Some("AUTO_MODERATION_ACTION_EXECUTION")) => Self::AUTO_MODERATION_ACTION_EXECUTION,
+            (OpCode::Dispatch, Some("AUTO_MODERATION_RULE_CREATE")) => Self::AUTO_MODERATION_RULE_CREATE,
+            (OpCode::Dispatch, Some("AUTO_MODERATION_RULE_DELETE")) => Self::AUTO_MODERATION_RULE_DELETE,
+            (OpCode::Dispatch, Some("AUTO_MODERATION_RULE_UPDATE")) => Self::AUTO_MODERATION_RULE_UPDATE,
+            (OpCode::Dispatch, Some("CHANNEL_CREATE")) => Self::CHANNEL_CREATE,
+            (OpCode::Dispatch, Some("CHANNEL_DELETE")) => Self::CHANNEL_DELETE,
+            (OpCode::Dispatch, Some("CHANNEL_PINS_UPDATE")) => Self::CHANNEL_PINS_UPDATE,
+            (OpCode::Dispatch, Some("CHANNEL_UPDATE")) => Self::CHANNEL_UPDATE,
+            (OpCode::Dispatch, Some("GIFT_CODE_UPDATE")) => Self::GIFT_CODE_UPDATE,
+            (OpCode::Dispatch, Some("GUILD_AUDIT_LOG_ENTRY_CREATE")) => Self::GUILD_AUDIT_LOG_ENTRY_CREATE,
+            (OpCode::Dispatch, Some("GUILD_BAN_ADD")) => Self::GUILD_BAN_ADD,
+            (OpCode::Dispatch, Some("GUILD_BAN_REMOVE")) => Self::GUILD_BAN_REMOVE,
+            (OpCode::Dispatch, Some("GUILD_CREATE")) => Self::GUILD_CREATE,
+            (OpCode::Dispatch, Some("GUILD_DELETE")) => Self::GUILD_DELETE,
+            (OpCode::Dispatch, Some("GUILD_EMOJIS_UPDATE")) => Self::GUILD_EMOJIS_UPDATE,
+            (OpCode::Dispatch, Some("GUILD_INTEGRATIONS_UPDATE")) => Self::GUILD_INTEGRATIONS_UPDATE,
+            (OpCode::Dispatch, Some("GUILD_MEMBERS_CHUNK")) => Self::GUILD_MEMBERS_CHUNK,
+            (OpCode::Dispatch, Some("GUILD_MEMBER_ADD")) => Self::GUILD_MEMBER_ADD,
+            (OpCode::Dispatch, Some("GUILD_MEMBER_REMOVE")) => Self::GUILD_MEMBER_REMOVE,
+            (OpCode::Dispatch, Some("GUILD_MEMBER_UPDATE")) => Self::GUILD_MEMBER_UPDATE,
+            (OpCode::Dispatch, Some("GUILD_ROLE_CREATE")) => Self::GUILD_ROLE_CREATE,
+            (OpCode::Dispatch, Some("GUILD_ROLE_DELETE")) => Self::GUILD_ROLE_DELETE,
+            (OpCode::Dispatch, Some("GUILD_ROLE_UPDATE")) => Self::GUILD_ROLE_UPDATE,
+            (OpCode::Dispatch, Some("GUILD_SCHEDULED_EVENT_CREATE")) => Self::GUILD_SCHEDULED_EVENT_CREATE,
+            (OpCode::Dispatch, Some("GUILD_SCHEDULED_EVENT_DELETE")) => Self::GUILD_SCHEDULED_EVENT_DELETE,
+            (OpCode::Dispatch, Some("GUILD_SCHEDULED_EVENT_UPDATE")) => Self::GUILD_SCHEDULED_EVENT_UPDATE,
+            (OpCode::Dispatch, Some("GUILD_SCHEDULED_EVENT_USER_ADD")) => Self::GUILD_SCHEDULED_EVENT_USER_ADD,
+            (OpCode::Dispatch, Some("GUILD_SCHEDULED_EVENT_USER_REMOVE")) => Self::GUILD_SCHEDULED_EVENT_USER_REMOVE,
+            (OpCode::Dispatch, Some("GUILD_STICKERS_UPDATE")) => Self::GUILD_STICKERS_UPDATE,
+            (OpCode::Dispatch, Some("GUILD_UPDATE")) => Self::GUILD_UPDATE,
+            (OpCode::Dispatch, Some("INTEGRATION_CREATE")) => Self::INTEGRATION_CREATE,
+            (OpCode::Dispatch, Some("INTEGRATION_DELETE")) => Self::INTEGRATION_DELETE,
+            (OpCode::Dispatch, Some("INTEGRATION_UPDATE")) => Self::INTEGRATION_UPDATE,
+            (OpCode::Dispatch, Some("INTERACTION_CREATE")) => Self::INTERACTION_CREATE,
+            (OpCode::Dispatch, Some("INVITE_CREATE")) => Self::INVITE_CREATE,
+            (OpCode::Dispatch, Some("INVITE_DELETE")) => Self::INVITE_DELETE,
+            (OpCode::Dispatch, Some("MESSAGE_CREATE")) => Self::MESSAGE_CREATE,
+            (OpCode::Dispatch, Some("MESSAGE_DELETE")) => Self::MESSAGE_DELETE,
+            (OpCode::Dispatch, Some("MESSAGE_DELETE_BULK")) => Self::MESSAGE_DELETE_BULK,
+            (OpCode::Dispatch, Some("MESSAGE_REACTION_ADD")) => Self::MESSAGE_REACTION_ADD,
+            (OpCode::Dispatch, Some("MESSAGE_REACTION_REMOVE")) => Self::MESSAGE_REACTION_REMOVE,
+            (OpCode::Dispatch, Some("MESSAGE_REACTION_REMOVE_ALL")) => Self::MESSAGE_REACTION_REMOVE_ALL,
+            (OpCode::Dispatch, Some("MESSAGE_REACTION_REMOVE_EMOJI")) => Self::MESSAGE_REACTION_REMOVE_EMOJI,
+            (OpCode::Dispatch, Some("MESSAGE_UPDATE")) => Self::MESSAGE_UPDATE,
+            (OpCode::Dispatch, Some("PRESENCES_REPLACE")) => Self::PRESENCES_REPLACE,
+            (OpCode::Dispatch, Some("PRESENCE_UPDATE")) => Self::PRESENCE_UPDATE,
+            (OpCode::Dispatch, Some("READY")) => Self::READY,
+            (OpCode::Dispatch, Some("RESUMED")) => Self::RESUMED,
+            (OpCode::Dispatch, Some("STAGE_INSTANCE_CREATE")) => Self::STAGE_INSTANCE_CREATE,
+            (OpCode::Dispatch, Some("STAGE_INSTANCE_DELETE")) => Self::STAGE_INSTANCE_DELETE,
+            (OpCode::Dispatch, Some("STAGE_INSTANCE_UPDATE")) => Self::STAGE_INSTANCE_UPDATE,
+            (OpCode::Dispatch, Some("THREAD_CREATE")) => Self::THREAD_CREATE,
+            (OpCode::Dispatch, Some("THREAD_DELETE")) => Self::THREAD_DELETE,
+            (OpCode::Dispatch, Some("THREAD_LIST_SYNC")) => Self::THREAD_LIST_SYNC,
+            (OpCode::Dispatch, Some("THREAD_MEMBERS_UPDATE")) => Self::THREAD_MEMBERS_UPDATE,
+            (OpCode::Dispatch, Some("THREAD_MEMBER_UPDATE")) => Self::THREAD_MEMBER_UPDATE,
+            (OpCode::Dispatch, Some("THREAD_UPDATE")) => Self::THREAD_UPDATE,
+            (OpCode::Dispatch, Some("TYPING_START")) => Self::TYPING_START,
+            (OpCode::Dispatch, Some("UNAVAILABLE_GUILD")) => Self::UNAVAILABLE_GUILD,
+            (OpCode::Dispatch, Some("USER_UPDATE")) => Self::USER_UPDATE,
+            (OpCode::Dispatch, Some("VOICE_SERVER_UPDATE")) => Self::VOICE_SERVER_UPDATE,
+            (OpCode::Dispatch, Some("VOICE_STATE_UPDATE")) => Self::VOICE_STATE_UPDATE,
+            (OpCode::Dispatch, Some("WEBHOOKS_UPDATE")) => Self::WEBHOOKS_UPDATE,
+            (OpCode::Heartbeat, _) => Self::GATEWAY_HEARTBEAT,
+            (OpCode::HeartbeatAck, _) => Self::GATEWAY_HEARTBEAT_ACK,
+            (OpCode::Hello, _) => Self::GATEWAY_HELLO,
+            (OpCode::InvalidSession, _) => Self::GATEWAY_INVALIDATE_SESSION,
+            (OpCode::Reconnect, _) => Self::GATEWAY_RECONNECT,
+            _ => return None,
+        };
+
+        Some(flag)
+    }
+}
+
+
+impl Default for EventTypeFlags {
+    fn default() -> Self {
+        Self::all()
+    }
+}
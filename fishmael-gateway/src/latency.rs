@@ -0,0 +1,83 @@
+use std::{collections::VecDeque, time::Duration};
+use tokio::time::Instant;
+
+
+/// Heartbeat round trip statistics of a shard's current connection.
+#[derive(Clone, Debug)]
+pub struct Latency {
+    heartbeats: u32,
+    recent: VecDeque<Duration>,
+    received: Option<Instant>,
+    sent: Option<Instant>,
+}
+
+
+impl Latency {
+    /// Number of round trips kept for the rolling average.
+    pub const RECENT_LEN: usize = 5;
+
+    pub(crate) fn new() -> Self {
+        Self {
+            heartbeats: 0,
+            recent: VecDeque::with_capacity(Self::RECENT_LEN),
+            received: None,
+            sent: None,
+        }
+    }
+
+    /// Average of the most recent round trips, if any heartbeat was acknowledged.
+    pub fn average(&self) -> Option<Duration> {
+        let total = self.recent.iter().sum::<Duration>();
+
+        u32::try_from(self.recent.len())
+            .ok()
+            .filter(|len| *len > 0)
+            .map(|len| total / len)
+    }
+
+    /// Number of heartbeats acknowledged on this connection.
+    pub const fn heartbeats(&self) -> u32 {
+        self.heartbeats
+    }
+
+    /// Round trip of the most recently acknowledged heartbeat.
+    pub fn latest(&self) -> Option<Duration> {
+        self.recent.back().copied()
+    }
+
+    /// Most recent round trips, oldest first.
+    pub fn recent(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.recent.iter().copied()
+    }
+
+    /// When the last heartbeat acknowledgement was received.
+    pub const fn received(&self) -> Option<Instant> {
+        self.received
+    }
+
+    /// When the last heartbeat was sent.
+    pub const fn sent(&self) -> Option<Instant> {
+        self.sent
+    }
+
+    pub(crate) fn record_sent(&mut self) {
+        self.sent = Some(Instant::now());
+    }
+
+    pub(crate) fn record_received(&mut self) {
+        let now = Instant::now();
+        let previous = self.received.replace(now);
+
+        // Only the first acknowledgement of a heartbeat counts as its round trip.
+        let Some(sent) = self.sent.filter(|sent| previous.is_none_or(|r| r < *sent)) else {
+            return;
+        };
+
+        if self.recent.len() == Self::RECENT_LEN {
+            self.recent.pop_front();
+        }
+
+        self.recent.push_back(now - sent);
+        self.heartbeats += 1;
+    }
+}
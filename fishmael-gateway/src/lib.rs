@@ -1,12 +1,12 @@
 use anyhow::{Context, Result};
 use error::ReceiveErrorKind;
 // use event::MinimalEvent;
-use futures::Sink;
+use futures::{future, Sink, StreamExt};
 use futures_core::Stream;
 use rand::{rngs::StdRng, Rng, SeedableRng};
 use serde::{de::DeserializeOwned, Deserialize};
 use std::{
-    env,
+    collections::HashSet,
     future::Future,
     io::ErrorKind as IoErrorKind,
     mem,
@@ -15,27 +15,24 @@ use std::{
     time::Duration,
 };
 use tokio::{
-    net::TcpStream,
-    time::{self, Instant, Interval, MissedTickBehavior}
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::{self, Instant, Interval, MissedTickBehavior, Sleep}
 };
-use tokio_tungstenite::{
-    connect_async,
-    tungstenite::{
-        protocol::frame::coding::CloseCode,
-        Error as WebsocketError,
-    },
-    MaybeTlsStream,
-    WebSocketStream,
+use tokio_tungstenite::tungstenite::{
+    protocol::frame::coding::CloseCode,
+    Error as WebsocketError,
+    Message as WebsocketMessage,
 };
 use twilight_model::gateway::{
-    event::GatewayEventDeserializer, payload::{
-        incoming::{Hello, Ready},
-        outgoing::{identify::{IdentifyInfo, IdentifyProperties},
+    payload::{
+        incoming::{Hello, MemberChunk, Ready},
+        outgoing::{identify::IdentifyInfo,
         Heartbeat,
         Identify,
         Resume}
     }, CloseCode as LibraryCloseCode, CloseFrame, OpCode
 };
+use twilight_model::id::{marker::GuildMarker, Id};
 
 pub use twilight_model::gateway::{
     Intents,
@@ -43,27 +40,52 @@ pub use twilight_model::gateway::{
     event::Event,
 };
 
+pub mod backoff;
+pub mod client;
 pub mod close_code;
+pub mod cluster;
+pub mod command;
+pub mod compression;
+pub mod config;
 pub mod deserialize;
+pub mod encoding;
 pub mod error;
 pub mod event;
+pub mod event_type;
+pub mod latency;
+pub mod members;
 pub mod message;
+pub mod notification;
 pub mod poll_event;
+pub mod queue;
+pub mod ratelimiter;
+pub mod sender;
+pub mod transport;
 
 use crate::{
-    error::ReceiveError,
+    compression::Decompressor,
+    config::{ShardBuilder, ShardConfig},
+    encoding::{Encoding, Payload},
+    error::{ReceiveError, ShardConfigError},
+    latency::Latency,
+    members::MemberRequests,
     poll_event::PollEvent,
+    queue::QueueFuture,
     message::Message,
+    notification::ShardNotification,
+    ratelimiter::CommandRatelimiter,
+    sender::ShardSender,
+    transport::Transport,
 };
 
 
-const GATEWAY_URL: &str = "wss://gateway.discord.gg";
-const API_VERSION: u8 = 10;
+/// How long [`Shard::close`] waits for the gateway's side of the close handshake.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
 
 
-type Connection = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type Connection = Box<dyn Transport>;
 
-struct ConnectionFuture(Pin<Box<dyn Future<Output = Result<Connection, WebsocketError>> + Send>>);
+struct ConnectionFuture(Pin<Box<dyn Future<Output = Result<Connection, ReceiveError>> + Send>>);
 
 
 #[derive(Deserialize)]
@@ -74,6 +96,17 @@ struct MinimalEvent<T> {
 }
 
 
+/// Guild of a GUILD_CREATE or GUILD_DELETE, ignoring everything but its
+/// ID and availability.
+#[derive(Deserialize)]
+struct MinimalGuild {
+    id: Id<GuildMarker>,
+    #[serde(default)]
+    unavailable: bool,
+}
+
+
+#[derive(Clone, Debug)]
 pub struct Session {
     id: Box<str>,
     sequence: u64,
@@ -102,6 +135,40 @@ impl Session {
 }
 
 
+/// Opening payload the shard owes the gateway on the current connection.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Handshake {
+    Identify,
+    Resume,
+}
+
+
+/// Message waiting to be sent over the connection.
+struct Pending {
+    /// Taken once handed to the connection, after which only flushing remains.
+    message: Option<Message>,
+    /// Heartbeats use the ratelimiter's reserved slots.
+    is_heartbeat: bool,
+}
+
+
+impl Pending {
+    const fn new(message: Message) -> Self {
+        Self {
+            message: Some(message),
+            is_heartbeat: false,
+        }
+    }
+
+    const fn heartbeat(message: Message) -> Self {
+        Self {
+            message: Some(message),
+            is_heartbeat: true,
+        }
+    }
+}
+
+
 #[derive(Clone, Debug)]
 enum CloseInitiator {
     Gateway(Option<u16>),
@@ -132,44 +199,109 @@ impl ShardState {
 
 
 pub struct Shard {
+    /// Whether the user closed the shard, which ends the stream.
+    closed: bool,
+    /// Messages queued through [`ShardSender`]s.
+    commands: UnboundedReceiver<Message>,
+    commands_tx: UnboundedSender<Message>,
+    config: ShardConfig,
     connection: Option<Connection>,
     connection_future: Option<ConnectionFuture>,
+    /// Decompression context of the current connection.
+    decompressor: Option<Decompressor>,
+    /// Handshake to send once HELLO was received, or after an invalidated session.
+    handshake: Option<Handshake>,
+    /// Delay before the next handshake after an invalidated session.
+    handshake_delay: Option<Pin<Box<Sleep>>>,
+    /// Deadline for HELLO, or for READY/RESUMED once the handshake was sent.
+    handshake_timeout: Option<Pin<Box<Sleep>>>,
     heartbeat_interval: Option<Interval>,
-    identified: bool,
-    intents: Intents,
-    pending: Option<Message>,
+    /// Turn in the identify queue the shard waits on before identifying.
+    identify_queue: Option<QueueFuture>,
+    /// Whether the gateway acknowledged the last heartbeat we sent.
+    last_heartbeat_acked: bool,
+    latency: Latency,
+    /// Member requests of [`ShardSender`]s awaiting their chunks.
+    member_requests: MemberRequests,
+    /// Whether all guilds announced by READY arrived, or stopped being waited on.
+    loaded: bool,
+    /// Deadline for the guilds announced by READY.
+    load_timeout: Option<Pin<Box<Sleep>>>,
+    /// Receiver of [`ShardNotification`]s, if anyone asked for them.
+    notifications: Option<UnboundedSender<ShardNotification>>,
+    pending: Option<Pending>,
+    /// Guilds announced by READY that the gateway hasn't sent yet.
+    pending_guilds: HashSet<Id<GuildMarker>>,
+    ratelimiter: Option<CommandRatelimiter>,
+    /// Connections attempted since the last READY or RESUMED, whether or not
+    /// they were established.
+    reconnect_attempts: u8,
+    /// Delay before the next connection attempt.
+    reconnect_delay: Option<Pin<Box<Sleep>>>,
     resume_gateway_url: Option<String>,
     rng: StdRng,
     session: Option<Session>,
     shard_id: ShardId,
     state: ShardState,
-    token: String,
+    /// Guilds in an outage, announced by an unavailable GUILD_CREATE or GUILD_DELETE.
+    unavailable_guilds: HashSet<Id<GuildMarker>>,
 }
 
 
 impl Shard {
 
+    /// Create a shard with the default [`ShardConfig`].
+    ///
+    /// Use [`ShardBuilder`](config::ShardBuilder) to customise identify and connection options.
     pub fn new(
         token: String,
         shard_id: ShardId,
         intents: Intents,
-    ) -> Self {
+    ) -> Result<Self, ShardConfigError> {
+        ShardBuilder::new(token, intents).build(shard_id)
+    }
+
+    /// Create a shard with a config that was validated by [`ShardBuilder`].
+    pub(crate) fn with_config(shard_id: ShardId, config: ShardConfig) -> Self {
+        let (commands_tx, commands) = mpsc::unbounded_channel();
+
         Self {
+            closed: false,
+            commands,
+            commands_tx,
+            config,
             connection: None,
             connection_future: None,
+            decompressor: None,
+            handshake: None,
+            handshake_delay: None,
+            handshake_timeout: None,
             heartbeat_interval: None,
-            identified: false,
-            intents,
+            identify_queue: None,
+            last_heartbeat_acked: true,
+            latency: Latency::new(),
+            loaded: false,
+            load_timeout: None,
+            member_requests: MemberRequests::default(),
+            notifications: None,
             pending: None,
+            pending_guilds: HashSet::new(),
+            ratelimiter: None,
+            reconnect_attempts: 0,
+            reconnect_delay: None,
             resume_gateway_url: None,
             rng: StdRng::from_entropy(),
             session: None,
             shard_id,
             state: ShardState::Disconnected{reconnect_attempts: 0},
-            token,
+            unavailable_guilds: HashSet::new(),
         }
     }
 
+    pub fn config(&self) -> &ShardConfig {
+        &self.config
+    }
+
     pub fn id(&self) -> ShardId {
         self.shard_id
     }
@@ -178,11 +310,88 @@ impl Shard {
         self.state
     }
 
+    /// Ratelimiter of the current connection, available once the gateway said HELLO.
+    pub fn ratelimiter(&self) -> Option<&CommandRatelimiter> {
+        self.ratelimiter.as_ref()
+    }
+
+    /// Handle for queueing commands from other tasks.
+    pub fn sender(&self) -> ShardSender {
+        ShardSender::new(self.commands_tx.clone(), self.member_requests.clone())
+    }
+
+    /// Heartbeat round trip statistics of the current connection.
+    pub fn latency(&self) -> &Latency {
+        &self.latency
+    }
+
+    /// Guilds announced by READY that the gateway hasn't sent yet.
+    pub fn pending_guilds(&self) -> &HashSet<Id<GuildMarker>> {
+        &self.pending_guilds
+    }
+
+    /// Guilds currently in an outage.
+    ///
+    /// Unlike guilds the bot was removed from, these come back once the
+    /// outage ends, so their cached data should be kept.
+    pub fn unavailable_guilds(&self) -> &HashSet<Id<GuildMarker>> {
+        &self.unavailable_guilds
+    }
+
+    /// Whether the session is active and its guilds were loaded.
+    ///
+    /// Guilds count as loaded once all guilds announced by READY arrived, or
+    /// the config's guild load timeout passed.
+    pub fn is_loaded(&self) -> bool {
+        self.state == ShardState::Active && self.loaded
+    }
+
+    /// Receive notifications about the shard's guilds.
+    ///
+    /// Only the latest receiver is notified; notifications are dropped while
+    /// nobody asked for them.
+    pub fn notifications(&mut self) -> UnboundedReceiver<ShardNotification> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.notifications = Some(tx);
+        rx
+    }
+
+    fn notify(&mut self, notification: ShardNotification) {
+        if self.notifications
+            .as_ref()
+            .is_some_and(|tx| tx.send(notification).is_err())
+        {
+            self.notifications = None;
+        }
+    }
+
+    /// Finish loading once the last guild announced by READY arrived.
+    fn check_loaded(&mut self) {
+        if !self.loaded && self.state == ShardState::Active && self.pending_guilds.is_empty() {
+            self.loaded = true;
+            self.load_timeout = None;
+            self.notify(ShardNotification::Loaded { missing: Vec::new() });
+        }
+    }
+
     fn disconnect(&mut self, initiator: CloseInitiator) {
+        self.handshake = None;
+        self.handshake_delay = None;
+        self.handshake_timeout = None;
         self.heartbeat_interval = None;
+        self.identify_queue = None;
+        // Restarted by RESUMED, so a dropped shard doesn't finish loading.
+        self.load_timeout = None;
+        self.ratelimiter = None;
+        // Connections that drop before READY or RESUMED keep backing off.
         self.state = match initiator {
-            CloseInitiator::Gateway(Some(close_code)) => ShardState::from_close_code(close_code),
-            _ => ShardState::Disconnected{reconnect_attempts: 0},
+            CloseInitiator::Gateway(Some(close_code)) => match ShardState::from_close_code(close_code) {
+                ShardState::Disconnected { .. } => ShardState::Disconnected {
+                    reconnect_attempts: self.reconnect_attempts,
+                },
+                state => state,
+            },
+            _ => ShardState::Disconnected{reconnect_attempts: self.reconnect_attempts},
         };
 
         if let CloseInitiator::Shard(frame) = initiator {
@@ -191,31 +400,65 @@ impl Shard {
                 self.resume_gateway_url = None;
                 self.session = None;
             }
-            self.pending = Some(Message::Close(Some(frame)));
+            self.pending = Some(Pending::new(Message::Close(Some(frame))));
+        }
+    }
+
+    /// Decompress a binary frame into a text message, or a binary one
+    /// holding an ETF payload.
+    ///
+    /// Returns `None` while a compressed message spans further frames.
+    fn decode_binary(&mut self, frame: &[u8]) -> Result<Option<Message>, ReceiveError> {
+        let payload = match self.decompressor.as_mut() {
+            Some(decompressor) => match decompressor.decompress(frame) {
+                Ok(Some(payload)) => payload,
+                Ok(None) => return Ok(None),
+                Err(err) => {
+                    // The compression context spans the connection, so
+                    // it can't recover from a corrupt frame.
+                    self.disconnect(CloseInitiator::Transport);
+                    self.connection = None;
+
+                    return Err(ReceiveError {
+                        kind: ReceiveErrorKind::Decompressing,
+                        source: Some(err),
+                    });
+                },
+            },
+            None => frame.to_vec(),
+        };
+
+        match self.config.encoding() {
+            Encoding::Json => String::from_utf8(payload)
+                .map(|json| Some(Message::Text(json)))
+                .map_err(|err| ReceiveError {
+                    kind: ReceiveErrorKind::Decoding,
+                    source: Some(Box::new(err)),
+                }),
+            Encoding::Etf => Ok(Some(Message::Binary(payload))),
         }
     }
 
     fn parse_event<T: DeserializeOwned>(
-        json: &str,
+        event: Payload<'_>,
     ) -> Result<MinimalEvent<T>, ReceiveError> {
-        serde_json::from_str::<MinimalEvent<T>>(json).map_err(|source| ReceiveError {
+        event.deserialize::<MinimalEvent<T>>().map_err(|source| ReceiveError {
             kind: ReceiveErrorKind::Deserializing {
-                event: json.to_owned(),
+                event: event.to_string(),
             },
-            source: Some(Box::new(source)),
+            source: Some(source),
         })
     }
 
-    fn process(&mut self, event: &str) -> Result<()> {
-        let (raw_opcode, maybe_sequence, maybe_event_type) =
-        GatewayEventDeserializer::from_json(event)
+    fn process(&mut self, event: Payload<'_>) -> Result<()> {
+        let (raw_opcode, maybe_sequence, maybe_event_type) = event
+            .parts()
             .ok_or(ReceiveError {
                 kind: ReceiveErrorKind::Deserializing {
-                    event: event.to_owned(),
+                    event: event.to_string(),
                 },
                 source: Some("missing opcode".into()),
-            })?
-            .into_parts();
+            })?;
 
 
         match OpCode::from(raw_opcode) {
@@ -230,12 +473,56 @@ impl Shard {
                         let event = Self::parse_event::<Ready>(event)
                             .context("failed to deserialise ready event")?;
 
+                        self.pending_guilds = event.data.guilds.iter().map(|guild| guild.id).collect();
+                        // The bot was removed from guilds missing in READY
+                        // while it was disconnected.
+                        let pending_guilds = &self.pending_guilds;
+                        self.unavailable_guilds.retain(|id| pending_guilds.contains(id));
                         self.resume_gateway_url = Some(event.data.resume_gateway_url);
                         self.session = Some(Session::new(sequence, event.data.session_id));
+                        self.handshake_timeout = None;
+                        self.reconnect_attempts = 0;
+                        self.loaded = false;
+                        self.load_timeout = Some(Box::pin(time::sleep(self.config.guild_load_timeout())));
                         self.state = ShardState::Active;
+                        self.check_loaded();
                     },
                     "RESUMED" => {
+                        self.handshake_timeout = None;
+                        self.reconnect_attempts = 0;
                         self.state = ShardState::Active;
+
+                        // The guilds still missing are replayed like any other event.
+                        if !self.loaded {
+                            self.load_timeout = Some(Box::pin(time::sleep(self.config.guild_load_timeout())));
+                            self.check_loaded();
+                        }
+                    }
+                    "GUILD_MEMBERS_CHUNK" if !self.member_requests.lock().unwrap().is_empty() => {
+                        let event = Self::parse_event::<MemberChunk>(event)
+                            .context("failed to deserialise member chunk")?;
+                        let chunk = event.data;
+
+                        let requests = self.member_requests.lock().unwrap();
+                        if let Some(tx) = chunk.nonce.as_ref().and_then(|nonce| requests.get(nonce)) {
+                            _ = tx.send(chunk);
+                        }
+                    }
+                    "GUILD_CREATE" | "GUILD_DELETE" => {
+                        let event = Self::parse_event::<MinimalGuild>(event)
+                            .context("failed to deserialise guild")?;
+                        let guild = event.data;
+
+                        if guild.unavailable {
+                            if self.unavailable_guilds.insert(guild.id) {
+                                self.notify(ShardNotification::GuildOutageBegan { guild_id: guild.id });
+                            }
+                        } else if self.unavailable_guilds.remove(&guild.id) && event_type == "GUILD_CREATE" {
+                            self.notify(ShardNotification::GuildOutageEnded { guild_id: guild.id });
+                        }
+
+                        self.pending_guilds.remove(&guild.id);
+                        self.check_loaded();
                     }
                     _ => {}
                 }
@@ -245,16 +532,18 @@ impl Shard {
                 }
             },
             Some(OpCode::Heartbeat) => {
-                self.pending = Some(Message::Text(
+                self.pending = Some(Pending::heartbeat(Message::Text(
                     serde_json::to_string(
                         &Heartbeat::new(self.session.as_ref().map(|s| s.sequence)),
                     )
                     .expect("failed to serialise heartbeat")
-                ));
+                )));
+                self.latency.record_sent();
             },
             Some(OpCode::HeartbeatAck) => {
                 println!("heartbeat ack received.");
-                // TODO: track heartbeat responses to check if connection is still alive.
+                self.last_heartbeat_acked = true;
+                self.latency.record_received();
             }
             Some(OpCode::Hello) => {
                 let event = Self::parse_event::<Hello>(event)
@@ -266,19 +555,41 @@ impl Shard {
                 let mut interval = time::interval_at(Instant::now() + jitter, heartbeat_interval);
                 interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
                 self.heartbeat_interval = Some(interval);
+                self.last_heartbeat_acked = true;
+                self.ratelimiter = Some(CommandRatelimiter::new(heartbeat_interval));
+                // Restarted for READY/RESUMED once the handshake is sent.
+                self.handshake_timeout = None;
 
-                if let Some(session) = &self.session {
-                    self.pending = Some(Message::Text(
-                        serde_json::to_string(&Resume::new(
-                            session.sequence(),
-                            session.id(),
-                            self.token.clone(),
-                        ))
-                        .expect("failed to serialise resume event"),
-                    ));
+                if self.session.is_some() {
+                    self.handshake = Some(Handshake::Resume);
                     self.state = ShardState::Resuming;
+                } else {
+                    self.handshake = Some(Handshake::Identify);
+                    self.state = ShardState::Identifying;
                 }
             }
+            Some(OpCode::InvalidSession) => {
+                let event = Self::parse_event::<bool>(event)
+                    .context("failed to deserialise invalid session event")?;
+
+                println!("session invalidated; resumable={}", event.data);
+
+                if event.data && self.session.is_some() {
+                    self.handshake = Some(Handshake::Resume);
+                    self.state = ShardState::Resuming;
+                } else {
+                    self.resume_gateway_url = None;
+                    self.session = None;
+                    self.handshake = Some(Handshake::Identify);
+                    self.state = ShardState::Identifying;
+                }
+
+                // Discord asks for a random delay of 1 to 5 seconds before the
+                // next resume or identify.
+                let delay = Duration::from_millis(self.rng.gen_range(1_000..=5_000));
+                self.handshake_delay = Some(Box::pin(time::sleep(delay)));
+                self.handshake_timeout = None;
+            }
             Some(OpCode::Reconnect) => {
                 println!("Got reconnect!");
                 self.disconnect(CloseInitiator::Shard(CloseFrame::RESUME));
@@ -289,6 +600,74 @@ impl Shard {
         Ok(())
     }
 
+    /// Seed a saved session so the first connection resumes it.
+    pub(crate) fn resume(&mut self, session: Session, resume_gateway_url: String) {
+        self.session = Some(session);
+        self.resume_gateway_url = Some(resume_gateway_url);
+    }
+
+    /// Reset per-connection state after a new connection was established.
+    ///
+    /// Nothing is sent until the gateway says HELLO.
+    fn on_connected(&mut self) {
+        // Whatever the last connection failed to send was meant for it, and
+        // would precede HELLO on this one.
+        self.pending = None;
+        self.handshake = None;
+        self.handshake_delay = None;
+        self.handshake_timeout = Some(Box::pin(time::sleep(self.config.hello_timeout())));
+        self.identify_queue = None;
+        self.decompressor = Decompressor::new(self.config.compression());
+        self.last_heartbeat_acked = true;
+        self.latency = Latency::new();
+        self.state = ShardState::Identifying;
+    }
+
+    /// Wait for the shard's turn in the identify queue if it is due to identify.
+    fn poll_identify_queue(&mut self, cx: &mut AsyncContext<'_>) -> Poll<()> {
+        if self.handshake != Some(Handshake::Identify) {
+            return Poll::Ready(());
+        }
+
+        let queue = self.identify_queue
+            .get_or_insert_with(|| self.config.queue().enqueue(self.shard_id));
+        ready!(queue.as_mut().poll(cx));
+        self.identify_queue = None;
+
+        Poll::Ready(())
+    }
+
+    /// Take the handshake that is due, serialised and ready to send.
+    fn take_handshake(&mut self) -> Option<Message> {
+        let json = match self.handshake.take()? {
+            Handshake::Identify => serde_json::to_string(&Identify::new(IdentifyInfo {
+                // Payload compression; transport compression is set in the URL.
+                compress: false,
+                intents: self.config.intents(),
+                large_threshold: self.config.large_threshold(),
+                presence: self.config.presence().cloned(),
+                properties: self.config.identify_properties().clone(),
+                shard: Some(self.shard_id),
+                token: self.config.token().to_owned(),
+            }))
+            .expect("failed to serialise identify"),
+            Handshake::Resume => {
+                let session = self.session
+                    .as_ref()
+                    .expect("resume is only queued with a session");
+
+                serde_json::to_string(&Resume::new(
+                    session.sequence(),
+                    session.id(),
+                    self.config.token().to_owned(),
+                ))
+                .expect("failed to serialise resume event")
+            },
+        };
+
+        Some(Message::Text(json))
+    }
+
     fn poll_handle_pending(&mut self, cx: &mut AsyncContext<'_>) -> Poll<Result<(), WebsocketError>> {
         println!("Polling pending event...");
 
@@ -297,23 +676,41 @@ impl Shard {
             return Poll::Ready(Ok(()));
         }
 
-        println!("Polling connection state for sending...");
-        if let Err(e) = ready!(Pin::new(self.connection.as_mut().unwrap()).poll_ready(cx)) {
-            println!("Polling connection state for sending... failed!");
+        if self.pending.as_ref().is_some_and(|pending| pending.message.is_some()) {
+            println!("Polling connection state for sending...");
+            if let Err(e) = ready!(Pin::new(self.connection.as_mut().unwrap()).poll_ready(cx)) {
+                println!("Polling connection state for sending... failed!");
 
-            self.disconnect(CloseInitiator::Transport);
-            self.connection = None;
-            return Poll::Ready(Err(e));
-        }
-        println!("Polling connection state for sending... done!");
+                self.disconnect(CloseInitiator::Transport);
+                self.connection = None;
+                return Poll::Ready(Err(e));
+            }
+            println!("Polling connection state for sending... done!");
 
-        let pending = self.pending.as_mut();
+            let pending = self.pending.as_mut().unwrap();
 
-        println!("sending event...");
-        if let Some(_message) = &pending {
-            // TODO: ratelimiting
+            // Close frames aren't gateway commands, so they don't count.
+            if let Some(ratelimiter) = self.ratelimiter.as_mut() {
+                if pending.is_heartbeat {
+                    ratelimiter.acquire_heartbeat();
+                } else if pending.message.as_ref().is_some_and(Message::is_text) {
+                    ready!(ratelimiter.poll_acquire(cx));
+                }
+            }
 
-            let ws_message = pending.unwrap().clone().into_websocket_msg();
+            println!("sending event...");
+            let ws_message = match (self.config.encoding(), pending.message.take().unwrap()) {
+                (Encoding::Etf, Message::Text(json)) => match encoding::encode(&json) {
+                    Ok(etf) => WebsocketMessage::Binary(etf),
+                    Err(err) => {
+                        println!("dropping message that can't be encoded as ETF: {}", err);
+
+                        self.pending = None;
+                        return Poll::Ready(Ok(()));
+                    },
+                },
+                (_, message) => message.into_websocket_msg(),
+            };
             if let Err(e) = Pin::new(self.connection.as_mut().unwrap()).start_send(ws_message) {
                 println!("sending event... failed!");
 
@@ -338,8 +735,43 @@ impl Shard {
         Poll::Ready(Ok(()))
     }
 
-    pub fn next_event(&mut self) -> PollEvent<Self> {
-        PollEvent::new(self)
+    /// Close the connection with the given frame and end the stream.
+    ///
+    /// Messages received while closing are discarded, so a resumed session
+    /// replays them. When the frame keeps the session resumable, such as
+    /// [`CloseFrame::RESUME`], the session and its resume gateway URL are
+    /// returned, even if the close frame could not be sent.
+    pub async fn close(&mut self, frame: CloseFrame<'static>) -> Option<(Session, String)> {
+        self.closed = true;
+        self.connection_future = None;
+        self.disconnect(CloseInitiator::Shard(frame));
+
+        // A broken connection is dropped either way, so only the session matters.
+        if self.connection.is_some() {
+            if let Err(err) = future::poll_fn(|cx| self.poll_handle_pending(cx)).await {
+                println!("failed to send close frame: {}", err);
+            }
+        }
+        self.pending = None;
+
+        // Wait for the gateway to complete the close handshake.
+        if let Some(connection) = self.connection.as_mut() {
+            let drain = async {
+                while let Some(Ok(_)) = connection.next().await {}
+            };
+
+            if time::timeout(CLOSE_TIMEOUT, drain).await.is_err() {
+                println!("gateway did not complete the close handshake in time");
+            }
+        }
+        self.connection = None;
+
+        self.session.take().zip(self.resume_gateway_url.take())
+    }
+
+    pub fn next_event(&mut self) -> PollEvent<'_, Self> {
+        let event_types = self.config.event_types();
+        PollEvent::new(self, event_types)
     }
 
 }
@@ -348,35 +780,74 @@ impl Stream for Shard {
     type Item = Result<Message, ReceiveError>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut AsyncContext<'_>) -> Poll<Option<Self::Item>> {
+        if self.closed {
+            return Poll::Ready(None);
+        }
+
         let message = loop {
             // Ensure connection...
             println!("loop start; connection={:?}, connection_future={:?}", self.connection.is_none(), self.connection_future.is_none());
 
             match self.state {
                 ShardState::FatallyClosed => {
-                    _ = ready!(
-                        Pin::new(
-                            self.connection
-                                .as_mut()
-                                .expect("connection should still exist")
-                        )
-                        .poll_close(cx)
-                    );
+                    if let Some(connection) = self.connection.as_mut() {
+                        _ = ready!(Pin::new(connection).poll_close(cx));
+                    }
                     self.connection = None;
                     return Poll::Ready(None);
                 },
                 ShardState::Disconnected { reconnect_attempts } if self.connection.is_none() => {
+                    if self.connection_future.is_none() && reconnect_attempts > 0 {
+                        if self.config.backoff().is_exhausted(reconnect_attempts) {
+                            println!("giving up after {} reconnect attempts", reconnect_attempts);
+
+                            self.state = ShardState::FatallyClosed;
+                            continue;
+                        }
+
+                        if self.reconnect_delay.is_none() {
+                            let backoff = self.config.backoff();
+                            let delay = backoff.delay(reconnect_attempts, &mut self.rng);
+                            println!("reconnecting in {:?}...", delay);
+
+                            self.reconnect_delay = Some(Box::pin(time::sleep(delay)));
+                        }
+
+                        ready!(self.reconnect_delay.as_mut().unwrap().as_mut().poll(cx));
+                        self.reconnect_delay = None;
+                    }
+
                     if self.connection_future.is_none() {
                         println!("setting up connection...");
     
                         let base_url = self.resume_gateway_url
                             .as_deref()
-                            .unwrap_or(GATEWAY_URL);
+                            .unwrap_or(self.config.gateway_url());
             
-                        let gateway_url = format!("{base_url}/?v={API_VERSION}&encoding=json");
+                        let mut gateway_url = format!(
+                            "{base_url}/?v={}&encoding={}",
+                            self.config.api_version(),
+                            self.config.encoding().query_value(),
+                        );
+                        if let Some(compress) = self.config.compression().query_value() {
+                            gateway_url.push_str("&compress=");
+                            gateway_url.push_str(compress);
+                        }
         
+                        let connect_timeout = self.config.connect_timeout();
+                        let connect = self.config.connector().connect(&gateway_url);
                         self.connection_future = Some(ConnectionFuture(Box::pin(async move {
-                            Ok(connect_async(&gateway_url).await?.0)
+                            match time::timeout(connect_timeout, connect).await {
+                                Ok(Ok(connection)) => Ok(connection),
+                                Ok(Err(source)) => Err(ReceiveError {
+                                    kind: ReceiveErrorKind::Reconnect,
+                                    source: Some(source),
+                                }),
+                                Err(_) => Err(ReceiveError {
+                                    kind: ReceiveErrorKind::ConnectTimeout,
+                                    source: None,
+                                }),
+                            }
                         })));
     
                         println!("setting up connection... done!");
@@ -389,49 +860,66 @@ impl Stream for Shard {
                     // This code is only reachable after ready! returns a completed poll;
                     // i.e. after a successful connection
                     self.connection_future = None;
+                    self.reconnect_attempts = reconnect_attempts.saturating_add(1);
         
                     match res {
                         Ok(connection) => {
                             println!("connection established!");
     
                             self.connection = Some(connection);
-                            self.state = ShardState::Identifying;
+                            self.on_connected();
                         }
                         Err(err) => {
                             println!("connection failed!");
     
                             self.resume_gateway_url = None;
                             self.state = ShardState::Disconnected{
-                                reconnect_attempts: reconnect_attempts + 1
+                                reconnect_attempts: self.reconnect_attempts,
                             };
                             
-                            return Poll::Ready(Some(Err(ReceiveError {
-                                kind: ReceiveErrorKind::Reconnect,
-                                source: Some(Box::new(err)),
-                            })))
+                            return Poll::Ready(Some(Err(err)))
                         }
                     }
                 },
                 _ => {},
             }
 
-            // TODO: implement and handle user closing 
+            // Finish sending whatever a previous poll could not, so that it isn't
+            // overwritten by a heartbeat below.
+            if self.pending.is_some() && ready!(self.poll_handle_pending(cx)).is_err() {
+                return Poll::Ready(Some(Ok(Message::ABNORMAL_CLOSE)));
+            }
 
             println!("polling heartbeat...");
 
             if self.heartbeat_interval
                 .as_mut()
-                .map_or(false, |interval| interval.poll_tick(cx).is_ready())
+                .is_some_and(|interval| interval.poll_tick(cx).is_ready())
             {
+                if !self.last_heartbeat_acked {
+                    println!("previous heartbeat was not acknowledged; connection is zombied!");
+
+                    self.disconnect(CloseInitiator::Shard(CloseFrame::RESUME));
+
+                    // The gateway stopped responding, so make a single attempt at
+                    // sending the close frame instead of waiting on the handshake.
+                    _ = self.poll_handle_pending(cx);
+                    self.pending = None;
+                    self.connection = None;
+
+                    return Poll::Ready(Some(Ok(Message::Close(Some(CloseFrame::RESUME)))));
+                }
+
                 println!("sending heartbeat...");
-                // TODO: Handle zombied connection
-                self.pending = Some(Message::Text(
+                self.pending = Some(Pending::heartbeat(Message::Text(
                     serde_json::to_string(
                         &Heartbeat::new(self.session.as_ref().map(|s| s.sequence))
                     )
                     .expect("failed to serialise heartbeat")
-                ));
-                
+                )));
+                self.last_heartbeat_acked = false;
+                self.latency.record_sent();
+
                 println!("polling heartbeat status...");
 
                 if ready!(self.poll_handle_pending(cx)).is_err() {
@@ -443,42 +931,90 @@ impl Stream for Shard {
                 println!("sending heartbeat done!...");
             }
 
-            if !self.identified {
-                self.pending = Some(Message::Text(
-                    serde_json::to_string(
-                        &Identify::new(
-                            IdentifyInfo {
-                                compress: false,
-                                intents: self.intents.clone(),
-                                large_threshold: 250,
-                                presence: None,
-                                properties: IdentifyProperties {
-                                    browser: "fishmael".to_string(),
-                                    device: "fishmael".to_string(),
-                                    os: env::consts::OS.to_string(),
-                                },
-                                shard: Some(self.shard_id),
-                                token: self.token.as_mut().to_string(),
-                            })
-                        )
-                        .expect("failed to serialise identify")
-                    )
-                );
+            if self.handshake_delay
+                .as_mut()
+                .is_some_and(|delay| delay.as_mut().poll(cx).is_ready())
+            {
+                self.handshake_delay = None;
+            }
 
-                self.identified = true;
+            if self.handshake_delay.is_none() && self.poll_identify_queue(cx).is_ready() {
+                if let Some(message) = self.take_handshake() {
+                    println!("sending handshake...");
+                    self.pending = Some(Pending::new(message));
+                    self.handshake_timeout = Some(Box::pin(time::sleep(self.config.ready_timeout())));
 
-                if ready!(self.poll_handle_pending(cx)).is_err() {
-                    return Poll::Ready(Some(Ok(Message::ABNORMAL_CLOSE)));
+                    if ready!(self.poll_handle_pending(cx)).is_err() {
+                        return Poll::Ready(Some(Ok(Message::ABNORMAL_CLOSE)));
+                    }
                 }
             }
 
-            // TODO: send user gateway messages
+            if self.load_timeout
+                .as_mut()
+                .is_some_and(|timeout| timeout.as_mut().poll(cx).is_ready())
+            {
+                println!("{} guilds did not arrive in time", self.pending_guilds.len());
 
-            match ready!(Pin::new(self.connection.as_mut().unwrap()).poll_next(cx)) {
-                Some(Ok(message)) => {
-                    if let Some(message) = Message::from_websocket_msg(&message) {
-                        break message
+                self.loaded = true;
+                self.load_timeout = None;
+                let missing = self.pending_guilds.iter().copied().collect();
+                self.notify(ShardNotification::Loaded { missing });
+            }
+
+            if self.handshake_timeout
+                .as_mut()
+                .is_some_and(|timeout| timeout.as_mut().poll(cx).is_ready())
+            {
+                // The interval is set up by HELLO, so without it we're still
+                // waiting on the gateway to greet us.
+                let kind = if self.heartbeat_interval.is_none() {
+                    ReceiveErrorKind::HelloTimeout
+                } else {
+                    ReceiveErrorKind::ReadyTimeout
+                };
+                println!("gateway handshake timed out: {:?}", kind);
+
+                self.disconnect(CloseInitiator::Shard(CloseFrame::RESUME));
+
+                // Like a zombied connection, make a single attempt at closing it.
+                _ = self.poll_handle_pending(cx);
+                self.pending = None;
+                self.connection = None;
+
+                return Poll::Ready(Some(Err(ReceiveError { kind, source: None })));
+            }
+
+            // Commands are only accepted by the gateway once the session is
+            // established, and only taken once a slot is free, so the shard
+            // keeps heartbeating and reading while they wait in the channel.
+            if self.state == ShardState::Active {
+                while self.ratelimiter
+                    .as_mut()
+                    .map_or(Poll::Ready(()), |ratelimiter| ratelimiter.poll_available(cx))
+                    .is_ready()
+                {
+                    let Poll::Ready(Some(message)) = self.commands.poll_recv(cx) else {
+                        break;
+                    };
+
+                    self.pending = Some(Pending::new(message));
+
+                    if ready!(self.poll_handle_pending(cx)).is_err() {
+                        return Poll::Ready(Some(Ok(Message::ABNORMAL_CLOSE)));
                     }
+                }
+            }
+
+            match ready!(Pin::new(self.connection.as_mut().unwrap()).poll_next(cx)) {
+                Some(Ok(message)) => match Message::from_websocket_msg(message) {
+                    Some(Message::Binary(frame)) => match self.decode_binary(&frame) {
+                        Ok(Some(message)) => break message,
+                        Ok(None) => continue,
+                        Err(err) => return Poll::Ready(Some(Err(err))),
+                    },
+                    Some(message) => break message,
+                    None => {},
                 },
                 Some(Err(WebsocketError::Io(e)))
                     if e.kind() == IoErrorKind::UnexpectedEof
@@ -488,6 +1024,7 @@ impl Stream for Shard {
                 },
                 Some(Err(_)) => {
                     self.disconnect(CloseInitiator::Transport);
+                    self.connection = None;
                     return Poll::Ready(Some(Ok(Message::ABNORMAL_CLOSE)));
                 }
                 None => {
@@ -509,20 +1046,396 @@ impl Stream for Shard {
                 println!("received websocket close message");
                 if !matches!(self.state, ShardState::Disconnected{..}) {
                     self.disconnect(
-                        CloseInitiator::Gateway(frame.as_ref().map(|f| f.code.into()))
+                        CloseInitiator::Gateway(frame.as_ref().map(|f| f.code))
                     );
                 } 
             }
             Message::Text(event) => {
-                self.process(event).map_err(|e| {
+                self.process(Payload::Json(event)).map_err(|e| {
+                    ReceiveError {
+                        kind: ReceiveErrorKind::Reconnect,
+                        source: Some(e.into()),
+                    }
+                })?;
+            },
+            // Binary frames were decompressed above, leaving only ETF payloads.
+            Message::Binary(event) => {
+                self.process(Payload::Etf(event)).map_err(|e| {
                     ReceiveError {
                         kind: ReceiveErrorKind::Reconnect,
                         source: Some(e.into()),
                     }
                 })?;
             },
+            // The websocket answers pings by itself.
+            Message::Ping(_) | Message::Pong(_) => {},
         }
 
         Poll::Ready(Some(Ok(message)))
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event_type::EventTypeFlags,
+        transport::{ConnectFuture, Connector},
+    };
+    use tokio_tungstenite::tungstenite::protocol::CloseFrame as WebsocketCloseFrame;
+    use twilight_model::gateway::event::GatewayEventDeserializer;
+    use futures::channel::mpsc::{self as channel, UnboundedReceiver as ChannelReceiver, UnboundedSender as ChannelSender};
+    use std::{collections::VecDeque, sync::Mutex};
+
+    pub(crate) const HELLO: &str = r#"{"op":10,"d":{"heartbeat_interval":41250}}"#;
+    const RESUMED: &str = r#"{"op":0,"s":12,"t":"RESUMED","d":{}}"#;
+    pub(crate) const READY: &str = r#"{"op":0,"s":1,"t":"READY","d":{
+        "v":10,
+        "application":{"id":"1","flags":0},
+        "guilds":[],
+        "resume_gateway_url":"wss://resume.discord.gg",
+        "session_id":"new-session",
+        "user":{"id":"2","username":"fishmael","discriminator":"0000","avatar":null,"mfa_enabled":false}
+    }}"#;
+
+    fn opcode(message: Message) -> u8 {
+        let Message::Text(json) = message else {
+            panic!("handshake should be a text message");
+        };
+
+        GatewayEventDeserializer::from_json(&json)
+            .expect("handshake should have an opcode")
+            .op()
+    }
+
+    /// Transport fed by the test, recording what the shard sends.
+    pub(crate) struct ChannelTransport {
+        /// Whether the next `poll_ready` fails, as with a dropped connection.
+        fail_ready: bool,
+        incoming: ChannelReceiver<Result<WebsocketMessage, WebsocketError>>,
+        outgoing: ChannelSender<WebsocketMessage>,
+    }
+
+    impl Stream for ChannelTransport {
+        type Item = Result<WebsocketMessage, WebsocketError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut AsyncContext<'_>) -> Poll<Option<Self::Item>> {
+            self.incoming.poll_next_unpin(cx)
+        }
+    }
+
+    impl Sink<WebsocketMessage> for ChannelTransport {
+        type Error = WebsocketError;
+
+        fn poll_ready(mut self: Pin<&mut Self>, _: &mut AsyncContext<'_>) -> Poll<Result<(), Self::Error>> {
+            if mem::take(&mut self.fail_ready) {
+                return Poll::Ready(Err(WebsocketError::ConnectionClosed));
+            }
+
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(self: Pin<&mut Self>, item: WebsocketMessage) -> Result<(), Self::Error> {
+            self.outgoing.unbounded_send(item).map_err(|_| WebsocketError::ConnectionClosed)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut AsyncContext<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut AsyncContext<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Gateway's ends of a [`ChannelTransport`].
+    pub(crate) type Gateway = (
+        ChannelSender<Result<WebsocketMessage, WebsocketError>>,
+        ChannelReceiver<WebsocketMessage>,
+    );
+
+    pub(crate) fn channel_transport() -> (ChannelTransport, Gateway) {
+        let (gateway_tx, incoming) = channel::unbounded();
+        let (outgoing, gateway_rx) = channel::unbounded();
+        let transport = ChannelTransport {
+            fail_ready: false,
+            incoming,
+            outgoing,
+        };
+
+        (transport, (gateway_tx, gateway_rx))
+    }
+
+    /// Hands out its [`ChannelTransport`]s in order, one per connection.
+    pub(crate) struct ChannelConnector(Mutex<VecDeque<ChannelTransport>>);
+
+    impl ChannelConnector {
+        pub(crate) fn new(transports: impl IntoIterator<Item = ChannelTransport>) -> Self {
+            Self(Mutex::new(transports.into_iter().collect()))
+        }
+    }
+
+    impl Connector for ChannelConnector {
+        fn connect(&self, _: &str) -> ConnectFuture {
+            let transport = self.0.lock().unwrap().pop_front();
+
+            Box::pin(async move {
+                transport
+                    .map(|transport| Box::new(transport) as Box<dyn Transport>)
+                    .ok_or_else(|| "no transport left".into())
+            })
+        }
+    }
+
+    /// Shard connected to channels, with the gateway's sending and receiving ends.
+    fn channel_shard() -> (
+        Shard,
+        ChannelSender<Result<WebsocketMessage, WebsocketError>>,
+        ChannelReceiver<WebsocketMessage>,
+    ) {
+        let (transport, (gateway_tx, gateway_rx)) = channel_transport();
+
+        let shard = ShardBuilder::new("token".into(), Intents::GUILDS)
+            .connector(ChannelConnector::new([transport]))
+            .build(ShardId::ONE)
+            .unwrap();
+
+        (shard, gateway_tx, gateway_rx)
+    }
+
+    /// Drain the opcodes of everything the shard sent so far.
+    pub(crate) fn sent_opcodes(gateway_rx: &mut ChannelReceiver<WebsocketMessage>) -> Vec<u8> {
+        let mut sent = Vec::new();
+        while let Ok(Some(WebsocketMessage::Text(json))) = gateway_rx.try_next() {
+            sent.push(opcode(Message::Text(json)));
+        }
+
+        sent
+    }
+
+    fn resuming_shard() -> Shard {
+        ShardBuilder::new("token".into(), Intents::GUILDS)
+            .session(Session::new(10, "old-session".into()), "wss://resume.discord.gg".into())
+            .build(ShardId::ONE)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn identifies_after_hello() {
+        let mut shard = Shard::new("token".into(), ShardId::ONE, Intents::GUILDS).unwrap();
+
+        shard.on_connected();
+        assert!(shard.take_handshake().is_none(), "nothing is sent before HELLO");
+
+        shard.process(Payload::Json(HELLO)).unwrap();
+        assert_eq!(shard.state(), ShardState::Identifying);
+        assert_eq!(shard.take_handshake().map(opcode), Some(OpCode::Identify as u8));
+        assert!(shard.take_handshake().is_none(), "identify is sent once");
+
+        shard.process(Payload::Json(READY)).unwrap();
+        assert_eq!(shard.state(), ShardState::Active);
+        assert_eq!(shard.session.as_ref().map(Session::id), Some("new-session"));
+
+        // A connection that can't be resumed identifies again.
+        shard.disconnect(CloseInitiator::Shard(CloseFrame::NORMAL));
+        shard.on_connected();
+        shard.process(Payload::Json(HELLO)).unwrap();
+        assert_eq!(shard.take_handshake().map(opcode), Some(OpCode::Identify as u8));
+    }
+
+    #[tokio::test]
+    async fn identifies_over_transport() {
+        let (mut shard, gateway_tx, mut gateway_rx) = channel_shard();
+
+        gateway_tx.unbounded_send(Ok(WebsocketMessage::Text(HELLO.into()))).unwrap();
+        gateway_tx.unbounded_send(Ok(WebsocketMessage::Text(READY.into()))).unwrap();
+
+        assert!(matches!(shard.next().await, Some(Ok(Message::Text(_)))));
+        assert_eq!(shard.state(), ShardState::Identifying);
+
+        assert!(matches!(shard.next().await, Some(Ok(Message::Text(_)))));
+        assert_eq!(shard.state(), ShardState::Active);
+
+        let Some(WebsocketMessage::Text(identify)) = gateway_rx.next().await else {
+            panic!("shard should have identified");
+        };
+        assert_eq!(opcode(Message::Text(identify)), OpCode::Identify as u8);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drops_unsent_handshake_on_reconnect() {
+        let (mut broken, (first_tx, _first_rx)) = channel_transport();
+        broken.fail_ready = true;
+        let (transport, (gateway_tx, mut gateway_rx)) = channel_transport();
+
+        let mut shard = ShardBuilder::new("token".into(), Intents::GUILDS)
+            .connector(ChannelConnector::new([broken, transport]))
+            .build(ShardId::ONE)
+            .unwrap();
+
+        // Sending IDENTIFY fails, so the shard reconnects.
+        first_tx.unbounded_send(Ok(WebsocketMessage::Text(HELLO.into()))).unwrap();
+        assert!(matches!(shard.next().await, Some(Ok(Message::Text(_)))));
+        assert!(matches!(shard.next().await, Some(Ok(message)) if message == Message::ABNORMAL_CLOSE));
+
+        gateway_tx.unbounded_send(Ok(WebsocketMessage::Text(HELLO.into()))).unwrap();
+        assert!(matches!(shard.next().await, Some(Ok(Message::Text(_)))));
+        assert!(sent_opcodes(&mut gateway_rx).is_empty(), "nothing is sent before HELLO");
+
+        // Identifying waits for the queue, which frees up after five seconds.
+        assert!(time::timeout(Duration::from_secs(10), shard.next()).await.is_err());
+        let identifies = sent_opcodes(&mut gateway_rx)
+            .into_iter()
+            .filter(|op| *op == OpCode::Identify as u8)
+            .count();
+        assert_eq!(identifies, 1);
+    }
+
+    #[tokio::test]
+    async fn identifies_over_etf() {
+        let (transport, (gateway_tx, mut gateway_rx)) = channel_transport();
+        let mut shard = ShardBuilder::new("token".into(), Intents::GUILDS)
+            .connector(ChannelConnector::new([transport]))
+            .encoding(Encoding::Etf)
+            .build(ShardId::ONE)
+            .unwrap();
+
+        for json in [HELLO, READY] {
+            let etf = encoding::encode(json).unwrap();
+            gateway_tx.unbounded_send(Ok(WebsocketMessage::Binary(etf))).unwrap();
+        }
+
+        assert!(matches!(shard.next().await, Some(Ok(Message::Binary(_)))));
+        assert_eq!(shard.state(), ShardState::Identifying);
+        assert!(matches!(shard.next().await, Some(Ok(Message::Binary(_)))));
+        assert_eq!(shard.state(), ShardState::Active);
+        assert_eq!(shard.session.as_ref().map(Session::id), Some("new-session"));
+
+        let Some(WebsocketMessage::Binary(identify)) = gateway_rx.next().await else {
+            panic!("shard should have identified in ETF");
+        };
+        assert_eq!(Payload::Etf(&identify).parts().map(|(op, ..)| op), Some(OpCode::Identify as u8));
+    }
+
+    #[tokio::test]
+    async fn closes_fatally_on_authentication_failure() {
+        let (mut shard, gateway_tx, _gateway_rx) = channel_shard();
+
+        gateway_tx.unbounded_send(Ok(WebsocketMessage::Text(HELLO.into()))).unwrap();
+        gateway_tx.unbounded_send(Ok(WebsocketMessage::Close(Some(WebsocketCloseFrame {
+            code: CloseCode::Library(4004),
+            reason: "Authentication failed.".into(),
+        })))).unwrap();
+
+        shard.next().await;
+        let Some(Ok(Message::Close(Some(frame)))) = shard.next().await else {
+            panic!("the close frame should be passed through");
+        };
+        assert_eq!(frame.code, 4004);
+        assert_eq!(shard.state(), ShardState::FatallyClosed);
+    }
+
+    #[tokio::test]
+    async fn resumes_after_hello() {
+        let mut shard = resuming_shard();
+
+        shard.on_connected();
+        shard.process(Payload::Json(HELLO)).unwrap();
+        assert_eq!(shard.state(), ShardState::Resuming);
+        assert_eq!(shard.take_handshake().map(opcode), Some(OpCode::Resume as u8));
+        assert!(shard.take_handshake().is_none(), "resume is sent once");
+
+        shard.process(Payload::Json(RESUMED)).unwrap();
+        assert_eq!(shard.state(), ShardState::Active);
+        assert_eq!(shard.session.as_ref().map(Session::sequence), Some(12));
+    }
+
+    #[tokio::test]
+    async fn identifies_after_failed_resume() {
+        let mut shard = resuming_shard();
+
+        shard.on_connected();
+        shard.process(Payload::Json(HELLO)).unwrap();
+        assert_eq!(shard.take_handshake().map(opcode), Some(OpCode::Resume as u8));
+
+        shard.process(Payload::Json(r#"{"op":9,"d":false}"#)).unwrap();
+        assert_eq!(shard.state(), ShardState::Identifying);
+        assert!(shard.session.is_none());
+        assert!(shard.resume_gateway_url.is_none());
+        assert!(shard.handshake_delay.is_some(), "identify waits for a delay");
+        assert_eq!(shard.take_handshake().map(opcode), Some(OpCode::Identify as u8));
+
+        shard.process(Payload::Json(READY)).unwrap();
+        assert_eq!(shard.state(), ShardState::Active);
+        assert_eq!(shard.session.as_ref().map(Session::id), Some("new-session"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn heartbeats_while_commands_are_ratelimited() {
+        let (mut shard, gateway_tx, mut gateway_rx) = channel_shard();
+
+        gateway_tx.unbounded_send(Ok(WebsocketMessage::Text(HELLO.into()))).unwrap();
+        gateway_tx.unbounded_send(Ok(WebsocketMessage::Text(READY.into()))).unwrap();
+        shard.next().await;
+        shard.next().await;
+        assert_eq!(shard.state(), ShardState::Active);
+
+        // One command more than the ratelimiter allows right now.
+        let available = shard.ratelimiter().unwrap().available();
+        for _ in 0..=available {
+            shard.commands_tx.send(Message::Text(r#"{"op":8,"d":{}}"#.into())).unwrap();
+        }
+
+        gateway_tx.unbounded_send(Ok(WebsocketMessage::Text(r#"{"op":11}"#.into()))).unwrap();
+        let Some(Ok(Message::Text(ack))) = shard.next().await else {
+            panic!("shard should keep reading while a command waits");
+        };
+        assert_eq!(opcode(Message::Text(ack)), OpCode::HeartbeatAck as u8);
+        assert_eq!(shard.commands.len(), 1, "the waiting command stays in the channel");
+
+        // The first heartbeat is due within one interval, before a slot frees up.
+        assert!(time::timeout(Duration::from_millis(41250), shard.next()).await.is_err());
+        assert_eq!(shard.commands.len(), 1);
+
+        let sent = sent_opcodes(&mut gateway_rx);
+        assert_eq!(sent.len(), usize::from(available) + 2, "identify, commands and a heartbeat");
+        assert_eq!(sent.last(), Some(&(OpCode::Heartbeat as u8)));
+    }
+
+    #[tokio::test]
+    async fn handles_excluded_ready() {
+        let (transport, (gateway_tx, _gateway_rx)) = channel_transport();
+        let mut shard = ShardBuilder::new("token".into(), Intents::GUILDS)
+            .connector(ChannelConnector::new([transport]))
+            .event_types(EventTypeFlags::all() - EventTypeFlags::GATEWAY_HELLO - EventTypeFlags::READY)
+            .build(ShardId::ONE)
+            .unwrap();
+
+        gateway_tx.unbounded_send(Ok(WebsocketMessage::Text(HELLO.into()))).unwrap();
+        gateway_tx.unbounded_send(Ok(WebsocketMessage::Text(READY.into()))).unwrap();
+        gateway_tx.unbounded_send(Ok(WebsocketMessage::Text(r#"{"op":11}"#.into()))).unwrap();
+
+        // HELLO and READY are skipped, but still handled by the shard.
+        assert!(matches!(shard.next_event().await, Some(Ok(Event::GatewayHeartbeatAck))));
+        assert_eq!(shard.state(), ShardState::Active);
+        assert_eq!(shard.session.as_ref().map(Session::id), Some("new-session"));
+    }
+
+    #[tokio::test]
+    async fn backs_off_until_ready() {
+        let mut shard = Shard::new("token".into(), ShardId::ONE, Intents::GUILDS).unwrap();
+
+        // Connections that drop before READY count towards the backoff.
+        shard.reconnect_attempts = 2;
+        shard.on_connected();
+        shard.disconnect(CloseInitiator::Gateway(Some(4000)));
+        assert_eq!(shard.state(), ShardState::Disconnected { reconnect_attempts: 2 });
+
+        shard.on_connected();
+        shard.process(Payload::Json(HELLO)).unwrap();
+        shard.process(Payload::Json(READY)).unwrap();
+        shard.disconnect(CloseInitiator::Shard(CloseFrame::NORMAL));
+        assert_eq!(shard.state(), ShardState::Disconnected { reconnect_attempts: 0 });
+    }
+}
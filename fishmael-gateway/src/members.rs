@@ -0,0 +1,79 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::mpsc::UnboundedSender;
+use twilight_model::{
+    gateway::{payload::incoming::MemberChunk, presence::Presence},
+    guild::Member,
+    id::{
+        marker::{GuildMarker, UserMarker},
+        Id,
+    },
+};
+
+
+/// Time allowed between two chunks of a member request.
+pub const CHUNK_TIMEOUT: Duration = Duration::from_secs(10);
+
+
+/// Pending member requests by nonce, shared by a shard and its senders.
+pub(crate) type MemberRequests = Arc<Mutex<HashMap<String, UnboundedSender<MemberChunk>>>>;
+
+
+/// Members returned by a request, collected from all of its chunks.
+#[derive(Clone, Debug)]
+pub struct GuildMembers {
+    pub guild_id: Id<GuildMarker>,
+    pub members: Vec<Member>,
+    /// Requested user IDs that aren't members of the guild.
+    pub not_found: Vec<Id<UserMarker>>,
+    /// Presences of the members, if they were requested.
+    pub presences: Vec<Presence>,
+}
+
+
+impl GuildMembers {
+    pub(crate) const fn new(guild_id: Id<GuildMarker>) -> Self {
+        Self {
+            guild_id,
+            members: Vec::new(),
+            not_found: Vec::new(),
+            presences: Vec::new(),
+        }
+    }
+
+    pub(crate) fn extend(&mut self, chunk: MemberChunk) {
+        self.members.extend(chunk.members);
+        self.not_found.extend(chunk.not_found);
+        self.presences.extend(chunk.presences);
+    }
+}
+
+
+/// Removes a request from the pending ones once it finished or was dropped.
+pub(crate) struct Registration {
+    nonce: String,
+    requests: MemberRequests,
+}
+
+
+impl Registration {
+    pub(crate) fn new(
+        requests: MemberRequests,
+        nonce: String,
+        tx: UnboundedSender<MemberChunk>,
+    ) -> Self {
+        requests.lock().unwrap().insert(nonce.clone(), tx);
+
+        Self { nonce, requests }
+    }
+}
+
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.requests.lock().unwrap().remove(&self.nonce);
+    }
+}
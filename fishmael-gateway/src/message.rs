@@ -11,49 +11,71 @@ use twilight_model::gateway::CloseFrame;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Message {
+    /// ETF encoded payload. Compressed frames are decompressed by the shard
+    /// into this or [`Text`](Self::Text), depending on the encoding.
+    Binary(Vec<u8>),
     Close(Option<CloseFrame<'static>>),
+    /// Answered by the websocket; passed through for observability.
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
     Text(String),
 }
 
 impl Message {
     pub(crate) const ABNORMAL_CLOSE: Self = Self::Close(Some(CloseFrame::new(1006, "")));
 
+    pub const fn is_binary(&self) -> bool {
+        matches!(self, Self::Binary(_))
+    }
+
     pub const fn is_close(&self) -> bool {
         matches!(self, Self::Close(_))
     }
 
+    pub const fn is_ping(&self) -> bool {
+        matches!(self, Self::Ping(_))
+    }
+
+    pub const fn is_pong(&self) -> bool {
+        matches!(self, Self::Pong(_))
+    }
+
     pub const fn is_text(&self) -> bool {
         matches!(self, Self::Text(_))
     }
 
-    pub(crate) fn from_websocket_msg(msg: &WebsocketMessage) -> Option<Self> {
-        if msg.is_close() {
-            let (code, reason) = match msg {
-                WebsocketMessage::Close(Some(frame)) => (frame.code, frame.reason.clone()),
-                _ => unreachable!(),
-            };
-
-            let frame = (code == CloseCode::Status).then(|| CloseFrame {
-                code: code.into(),
-                reason: Cow::Owned(reason.to_string()),
-            });
-
-            Some(Self::Close(frame))
-        } else if msg.is_text() {
-            Some(Self::Text(msg.to_text().unwrap().to_owned()))
-        } else {
-            None
+    /// Convert a received message; raw frames are never received and map to `None`.
+    pub(crate) fn from_websocket_msg(msg: WebsocketMessage) -> Option<Self> {
+        match msg {
+            WebsocketMessage::Binary(data) => Some(Self::Binary(data)),
+            WebsocketMessage::Close(frame) => {
+                let frame = frame
+                    .filter(|frame| frame.code != CloseCode::Status)
+                    .map(|frame| CloseFrame {
+                        code: frame.code.into(),
+                        reason: Cow::Owned(frame.reason.into_owned()),
+                    });
+
+                Some(Self::Close(frame))
+            },
+            WebsocketMessage::Ping(data) => Some(Self::Ping(data)),
+            WebsocketMessage::Pong(data) => Some(Self::Pong(data)),
+            WebsocketMessage::Text(text) => Some(Self::Text(text)),
+            WebsocketMessage::Frame(_) => None,
         }
     }
 
     pub(crate) fn into_websocket_msg(self) -> WebsocketMessage {
         match self {
+            Self::Binary(data) => WebsocketMessage::Binary(data),
             Self::Close(frame) => WebsocketMessage::Close(
                 frame.map(|f| WebsocketCloseFrame{
-                    code: CloseCode::try_from(f.code).unwrap(),
+                    code: CloseCode::from(f.code),
                     reason: f.reason,
                 })
             ),
+            Self::Ping(data) => WebsocketMessage::Ping(data),
+            Self::Pong(data) => WebsocketMessage::Pong(data),
             Self::Text(string) => WebsocketMessage::text(string),
         }
     }
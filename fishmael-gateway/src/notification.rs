@@ -0,0 +1,22 @@
+use twilight_model::id::{marker::GuildMarker, Id};
+
+
+/// Change in a shard's view of its guilds, received through
+/// [`Shard::notifications`](crate::Shard::notifications).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ShardNotification {
+    /// A guild became unavailable. The bot is still in it, so its cached
+    /// data should be kept.
+    GuildOutageBegan {
+        guild_id: Id<GuildMarker>,
+    },
+    /// An unavailable guild is available again.
+    GuildOutageEnded {
+        guild_id: Id<GuildMarker>,
+    },
+    /// Every guild announced by READY arrived, or the guild load timeout
+    /// passed with the `missing` guilds yet to arrive.
+    Loaded {
+        missing: Vec<Id<GuildMarker>>,
+    },
+}
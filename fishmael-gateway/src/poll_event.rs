@@ -12,20 +12,38 @@ use std::{
 use twilight_model::gateway::event::Event;
 
 use crate::{
-    deserialize::deserialize,
+    deserialize::{deserialize, deserialize_etf},
+    event_type::EventTypeFlags,
     message::Message,
     error::{ReceiveError, ReceiveErrorKind},
 };
 
 
+/// Event carried by a message, if any.
+pub(crate) fn event_from_message(
+    message: Message,
+    event_types: EventTypeFlags,
+) -> Result<Option<Event>, ReceiveError> {
+    match message {
+        Message::Text(json) => deserialize(json, event_types).map(|o| o.map(Into::into)),
+        // Shards only pass on binary messages once decompressed, as ETF payloads.
+        Message::Binary(etf) => deserialize_etf(&etf, event_types).map(|o| o.map(Into::into)),
+        Message::Close(frame) => Ok(Some(Event::GatewayClose(frame))),
+        Message::Ping(_) | Message::Pong(_) => Ok(None),
+    }
+}
+
+
 pub struct PollEvent<'a, St: ?Sized> {
+    /// Events to deserialize; the others are skipped.
+    event_types: EventTypeFlags,
     stream: &'a mut St,
 }
 
 
 impl<'a, St: ?Sized> PollEvent<'a, St> {
-    pub fn new(stream: &'a mut St) -> Self {
-        Self{stream}
+    pub fn new(stream: &'a mut St, event_types: EventTypeFlags) -> Self {
+        Self{event_types, stream}
     }
 }
 
@@ -34,19 +52,16 @@ impl<'a, St: ?Sized + Stream<Item = Result<Message, ReceiveError>> + Unpin> Futu
     type Output = Option<Result<Event, ReceiveError>>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut AsyncContext<'_>) -> Poll<Self::Output> {
-        let try_from_message = |message| match message {
-            Message::Text(json) => deserialize(json).map(|o| o.map(Into::into)),
-            Message::Close(frame) => Ok(Some(Event::GatewayClose(frame))),
-        };
-        
+        let event_types = self.event_types;
+
         loop {
             match ready!(Pin::new(&mut self.stream).poll_next(cx)) {
                 Some(item) => {
-                    match item.and_then(try_from_message) {
+                    match item.and_then(|message| event_from_message(message, event_types)) {
                         Ok(Some(event)) => {
                             return Poll::Ready(Some(Ok(event)));
                         },
-                        Ok(None) => {println!("skipping event...");}
+                        Ok(None) => {},
                         Err(ReceiveError{kind: ReceiveErrorKind::Deserializing{event}, source: Some(source)}) => {
                             println!("failed to deserialise event: {}...\n\twith reason: {}", &event[..100], source);
 
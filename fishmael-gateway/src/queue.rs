@@ -0,0 +1,185 @@
+#[cfg(feature = "redis")]
+use redis::{aio::MultiplexedConnection, RedisResult};
+use std::{fmt::Debug, future::Future, pin::Pin, sync::Arc, time::Duration};
+use tokio::{
+    sync::Mutex,
+    time::{self, Instant},
+};
+use twilight_model::gateway::ShardId;
+
+
+/// Time Discord requires between identifies of the same bucket.
+pub const IDENTIFY_INTERVAL: Duration = Duration::from_secs(5);
+
+
+/// Future resolving once a shard may identify.
+pub type QueueFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+
+/// Orders the identifies of shards sharing a bot's identify limit.
+///
+/// Discord allows `max_concurrency` identifies per five seconds, one for each
+/// bucket `shard_id % max_concurrency`. Shards of one bot must share a queue.
+pub trait Queue: Debug + Send + Sync {
+    /// Wait for the turn of `shard_id` to identify.
+    fn enqueue(&self, shard_id: ShardId) -> QueueFuture;
+}
+
+
+/// Queue for the shards of a single process.
+#[derive(Clone, Debug)]
+pub struct InMemoryQueue {
+    /// Time of each bucket's last identify.
+    buckets: Arc<[Arc<Mutex<Option<Instant>>>]>,
+}
+
+
+impl InMemoryQueue {
+    /// Queue with `max_concurrency` buckets, as returned by `/gateway/bot`.
+    pub fn new(max_concurrency: u16) -> Self {
+        Self {
+            buckets: (0..max_concurrency.max(1))
+                .map(|_| Arc::new(Mutex::new(None)))
+                .collect(),
+        }
+    }
+
+    pub fn max_concurrency(&self) -> u16 {
+        self.buckets.len() as u16
+    }
+}
+
+
+impl Default for InMemoryQueue {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+
+impl Queue for InMemoryQueue {
+    fn enqueue(&self, shard_id: ShardId) -> QueueFuture {
+        let index = shard_id.number() % self.buckets.len() as u64;
+        let bucket = Arc::clone(&self.buckets[index as usize]);
+
+        Box::pin(async move {
+            // The lock is fair, so shards take their turns in order. It's held
+            // while waiting out the interval, and released if the shard stops
+            // waiting, e.g. after losing its connection.
+            let mut last = bucket.lock().await;
+
+            if let Some(last) = *last {
+                time::sleep_until(last + IDENTIFY_INTERVAL).await;
+            }
+
+            *last = Some(Instant::now());
+        })
+    }
+}
+
+
+/// Queue shared by processes through Redis.
+///
+/// Each bucket is a key that an identifying shard sets for five seconds,
+/// so no other shard of the bucket identifies until it expires.
+#[cfg(feature = "redis")]
+#[derive(Clone)]
+pub struct RedisQueue {
+    con: MultiplexedConnection,
+    max_concurrency: u16,
+    prefix: String,
+}
+
+
+#[cfg(feature = "redis")]
+impl RedisQueue {
+    /// Delay before retrying after Redis failed.
+    const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+    /// Queue with `max_concurrency` buckets, as returned by `/gateway/bot`.
+    ///
+    /// Keys are named `fishmael:identify:<bucket>`.
+    pub fn new(con: MultiplexedConnection, max_concurrency: u16) -> Self {
+        Self::with_prefix(con, max_concurrency, "fishmael:identify".to_owned())
+    }
+
+    /// Queue using the keys `<prefix>:<bucket>`, e.g. to run several bots on one Redis.
+    pub fn with_prefix(con: MultiplexedConnection, max_concurrency: u16, prefix: String) -> Self {
+        Self {
+            con,
+            max_concurrency: max_concurrency.max(1),
+            prefix,
+        }
+    }
+
+    pub const fn max_concurrency(&self) -> u16 {
+        self.max_concurrency
+    }
+
+    /// Claim the bucket, or return how long it is still claimed for.
+    async fn try_claim(con: &mut MultiplexedConnection, key: &str) -> RedisResult<Option<Duration>> {
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(1)
+            .arg("NX")
+            .arg("PX")
+            .arg(IDENTIFY_INTERVAL.as_millis() as u64)
+            .query_async(con)
+            .await?;
+
+        if claimed.is_some() {
+            return Ok(None);
+        }
+
+        let ttl: i64 = redis::cmd("PTTL").arg(key).query_async(con).await?;
+
+        Ok(Some(match ttl {
+            // The key expired in the meantime, so retry right away.
+            -2 => Duration::ZERO,
+            // The key lost its expiry, so it would never be claimable again.
+            -1 => {
+                redis::cmd("PEXPIRE")
+                    .arg(key)
+                    .arg(IDENTIFY_INTERVAL.as_millis() as u64)
+                    .query_async::<()>(con)
+                    .await?;
+
+                IDENTIFY_INTERVAL
+            },
+            ttl => Duration::from_millis(ttl.max(0) as u64),
+        }))
+    }
+}
+
+
+#[cfg(feature = "redis")]
+impl Debug for RedisQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisQueue")
+            .field("max_concurrency", &self.max_concurrency)
+            .field("prefix", &self.prefix)
+            .finish()
+    }
+}
+
+
+#[cfg(feature = "redis")]
+impl Queue for RedisQueue {
+    fn enqueue(&self, shard_id: ShardId) -> QueueFuture {
+        let key = format!("{}:{}", self.prefix, shard_id.number() % u64::from(self.max_concurrency));
+        let mut con = self.con.clone();
+
+        Box::pin(async move {
+            loop {
+                match Self::try_claim(&mut con, &key).await {
+                    Ok(None) => return,
+                    Ok(Some(ttl)) => time::sleep(ttl).await,
+                    Err(err) => {
+                        println!("failed to claim identify bucket {}: {}", key, err);
+                        time::sleep(Self::RETRY_DELAY).await;
+                    },
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    task::{ready, Context as AsyncContext, Poll},
+    time::Duration,
+};
+use tokio::time::{self, Instant, Sleep};
+
+
+/// Number of commands the gateway allows per [`PERIOD`].
+const COMMANDS_PER_PERIOD: u8 = 120;
+
+/// Window over which commands are counted.
+const PERIOD: Duration = Duration::from_secs(60);
+
+
+/// Sliding window ratelimiter for the messages a shard sends.
+///
+/// A part of the window is reserved for heartbeats, so queued commands can
+/// never delay them.
+#[derive(Debug)]
+pub struct CommandRatelimiter {
+    delay: Pin<Box<Sleep>>,
+    /// Number of slots per period reserved for heartbeats.
+    reserved: u8,
+    /// Send times of the messages in the current window, oldest first.
+    sent: VecDeque<Instant>,
+}
+
+
+impl CommandRatelimiter {
+    pub(crate) fn new(heartbeat_interval: Duration) -> Self {
+        // One extra slot for heartbeats requested by the gateway.
+        let heartbeats = PERIOD.as_millis().div_ceil(heartbeat_interval.as_millis().max(1)) + 1;
+        let reserved = u8::try_from(heartbeats)
+            .unwrap_or(COMMANDS_PER_PERIOD)
+            .min(COMMANDS_PER_PERIOD);
+
+        Self {
+            delay: Box::pin(time::sleep(Duration::ZERO)),
+            reserved,
+            sent: VecDeque::with_capacity(COMMANDS_PER_PERIOD.into()),
+        }
+    }
+
+    /// Number of commands that can currently be sent without waiting.
+    pub fn available(&self) -> u8 {
+        let now = Instant::now();
+        let in_window = self.sent
+            .iter()
+            .filter(|sent| now.duration_since(**sent) < PERIOD)
+            .count();
+
+        self.max().saturating_sub(u8::try_from(in_window).unwrap_or(u8::MAX))
+    }
+
+    /// Number of commands allowed per period, excluding reserved heartbeats.
+    pub const fn max(&self) -> u8 {
+        COMMANDS_PER_PERIOD - self.reserved
+    }
+
+    /// Record a heartbeat, which never waits for a slot.
+    pub(crate) fn acquire_heartbeat(&mut self) {
+        self.clear_expired();
+        self.sent.push_back(Instant::now());
+    }
+
+    /// Wait until a command may be sent, without recording it.
+    pub(crate) fn poll_available(&mut self, cx: &mut AsyncContext<'_>) -> Poll<()> {
+        loop {
+            self.clear_expired();
+
+            let commands = u8::try_from(self.sent.len()).unwrap_or(u8::MAX);
+            if commands < self.max() {
+                return Poll::Ready(());
+            }
+
+            let oldest = *self.sent.front().expect("window is full so it is not empty");
+            if self.delay.deadline() != oldest + PERIOD {
+                self.delay.as_mut().reset(oldest + PERIOD);
+            }
+
+            if self.delay.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+    }
+
+    /// Wait until a command may be sent and record it.
+    pub(crate) fn poll_acquire(&mut self, cx: &mut AsyncContext<'_>) -> Poll<()> {
+        ready!(self.poll_available(cx));
+        self.sent.push_back(Instant::now());
+
+        Poll::Ready(())
+    }
+
+    fn clear_expired(&mut self) {
+        let now = Instant::now();
+
+        while self.sent.front().is_some_and(|sent| now.duration_since(*sent) >= PERIOD) {
+            self.sent.pop_front();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+
+    const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(40);
+
+    #[tokio::test(start_paused = true)]
+    async fn reserves_heartbeat_slots() {
+        let ratelimiter = CommandRatelimiter::new(HEARTBEAT_INTERVAL);
+
+        // Two heartbeats per minute, plus one requested by the gateway.
+        assert_eq!(ratelimiter.max(), COMMANDS_PER_PERIOD - 3);
+        assert_eq!(ratelimiter.available(), ratelimiter.max());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_window_to_pass() {
+        let mut ratelimiter = CommandRatelimiter::new(HEARTBEAT_INTERVAL);
+
+        for _ in 0..ratelimiter.max() {
+            future::poll_fn(|cx| ratelimiter.poll_acquire(cx)).await;
+        }
+        assert_eq!(ratelimiter.available(), 0);
+        assert!(future::poll_fn(|cx| Poll::Ready(ratelimiter.poll_available(cx))).await.is_pending());
+
+        // Heartbeats still go through, and take up their own slots.
+        ratelimiter.acquire_heartbeat();
+
+        let start = Instant::now();
+        future::poll_fn(|cx| ratelimiter.poll_acquire(cx)).await;
+        assert_eq!(start.elapsed(), PERIOD);
+        assert_eq!(ratelimiter.available(), ratelimiter.max() - 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn available_does_not_record() {
+        let mut ratelimiter = CommandRatelimiter::new(HEARTBEAT_INTERVAL);
+
+        assert!(future::poll_fn(|cx| Poll::Ready(ratelimiter.poll_available(cx))).await.is_ready());
+        assert_eq!(ratelimiter.available(), ratelimiter.max());
+    }
+}
@@ -0,0 +1,230 @@
+use tokio::{
+    sync::mpsc::{self, UnboundedSender},
+    time,
+};
+use twilight_model::gateway::payload::outgoing::RequestGuildMembers;
+
+use crate::{
+    command::Command,
+    error::{MemberRequestError, MemberRequestErrorKind, SendError, SendErrorKind},
+    members::{GuildMembers, MemberRequests, Registration, CHUNK_TIMEOUT},
+    message::Message,
+};
+
+
+/// Cloneable handle for queueing commands on a shard while it is streaming.
+///
+/// Queued commands are sent once the shard is active, in the order they
+/// were queued.
+#[derive(Clone, Debug)]
+pub struct ShardSender {
+    member_requests: MemberRequests,
+    tx: UnboundedSender<Message>,
+}
+
+
+impl ShardSender {
+    pub(crate) const fn new(tx: UnboundedSender<Message>, member_requests: MemberRequests) -> Self {
+        Self { member_requests, tx }
+    }
+
+    /// Serialise and queue a command.
+    pub fn command(&self, command: &impl Command) -> Result<(), SendError> {
+        let json = serde_json::to_string(command).map_err(|source| SendError {
+            kind: SendErrorKind::Serializing,
+            source: Some(Box::new(source)),
+        })?;
+
+        self.send(json)
+    }
+
+    /// Queue a raw JSON gateway payload.
+    pub fn send(&self, json: String) -> Result<(), SendError> {
+        self.tx.send(Message::Text(json)).map_err(|_| SendError {
+            kind: SendErrorKind::Closed,
+            source: None,
+        })
+    }
+
+    /// Request guild members and collect every chunk of the response.
+    ///
+    /// The request's nonce is replaced by a generated one to tell its chunks
+    /// apart. Fails if [`CHUNK_TIMEOUT`] passes without a chunk arriving.
+    pub async fn request_members(
+        &self,
+        mut request: RequestGuildMembers,
+    ) -> Result<GuildMembers, MemberRequestError> {
+        // Nonces are limited to 32 characters.
+        let nonce = format!("{:032x}", rand::random::<u128>());
+        request.d.nonce = Some(nonce.clone());
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let _registration = Registration::new(self.member_requests.clone(), nonce, tx);
+
+        self.command(&request).map_err(|source| MemberRequestError {
+            kind: MemberRequestErrorKind::Sending,
+            source: Some(Box::new(source)),
+        })?;
+
+        let mut members = GuildMembers::new(request.d.guild_id);
+        let mut received = 0;
+
+        loop {
+            let Ok(Some(chunk)) = time::timeout(CHUNK_TIMEOUT, rx.recv()).await else {
+                return Err(MemberRequestError {
+                    kind: MemberRequestErrorKind::TimedOut,
+                    source: None,
+                });
+            };
+
+            received += 1;
+            let chunk_count = chunk.chunk_count;
+            members.extend(chunk);
+
+            if received >= chunk_count {
+                return Ok(members);
+            }
+        }
+    }
+
+    /// Whether the shard this handle belongs to has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        members::MemberRequests,
+        tests::{channel_transport, sent_opcodes, ChannelConnector, Gateway, HELLO, READY},
+        Intents,
+        ShardBuilder,
+        ShardId,
+    };
+    use futures::StreamExt;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tokio::task::JoinHandle;
+    use tokio_tungstenite::tungstenite::Message as WebsocketMessage;
+    use twilight_model::id::Id;
+
+    /// Shard that is active and driven in the background.
+    async fn active_shard() -> (ShardSender, MemberRequests, Gateway) {
+        let (transport, (gateway_tx, mut gateway_rx)) = channel_transport();
+        let mut shard = ShardBuilder::new("token".into(), Intents::GUILD_MEMBERS)
+            .connector(ChannelConnector::new([transport]))
+            .build(ShardId::ONE)
+            .unwrap();
+
+        gateway_tx.unbounded_send(Ok(WebsocketMessage::Text(HELLO.into()))).unwrap();
+        gateway_tx.unbounded_send(Ok(WebsocketMessage::Text(READY.into()))).unwrap();
+        shard.next().await;
+        shard.next().await;
+        sent_opcodes(&mut gateway_rx);
+
+        let sender = shard.sender();
+        let requests = Arc::clone(&shard.member_requests);
+        tokio::spawn(async move { while shard.next().await.is_some() {} });
+
+        (sender, requests, (gateway_tx, gateway_rx))
+    }
+
+    /// Request the members of guild 1, returning the request's nonce once it was sent.
+    async fn request(
+        sender: &ShardSender,
+        gateway: &mut Gateway,
+    ) -> (JoinHandle<Result<GuildMembers, MemberRequestError>>, String) {
+        let sender = sender.clone();
+        let task = tokio::spawn(async move {
+            sender.request_members(RequestGuildMembers::builder(Id::new(1)).query("", None)).await
+        });
+
+        let Some(WebsocketMessage::Text(json)) = gateway.1.next().await else {
+            panic!("the shard should have sent the request");
+        };
+        let payload: Value = serde_json::from_str(&json).unwrap();
+        let nonce = payload["d"]["nonce"].as_str().unwrap().to_owned();
+
+        (task, nonce)
+    }
+
+    fn send_chunk(gateway: &Gateway, nonce: &str, index: u32, count: u32, user_id: u64) {
+        let chunk = json!({
+            "op": 0,
+            "s": 2,
+            "t": "GUILD_MEMBERS_CHUNK",
+            "d": {
+                "guild_id": "1",
+                "chunk_index": index,
+                "chunk_count": count,
+                "nonce": nonce,
+                "members": [{
+                    "user": {"id": user_id.to_string(), "username": "member", "discriminator": "0000", "avatar": null},
+                    "roles": [],
+                    "joined_at": "2024-01-01T00:00:00.000000+00:00",
+                    "deaf": false,
+                    "mute": false,
+                    "flags": 0,
+                }],
+                "not_found": [(user_id + 100).to_string()],
+                "presences": [{
+                    "user": {"id": user_id.to_string()},
+                    "status": "online",
+                    "activities": [],
+                    "client_status": {},
+                }],
+            },
+        });
+
+        gateway.0.unbounded_send(Ok(WebsocketMessage::Text(chunk.to_string()))).unwrap();
+    }
+
+    #[tokio::test]
+    async fn collects_chunks_by_nonce() {
+        let (sender, requests, mut gateway) = active_shard().await;
+        let (task, nonce) = request(&sender, &mut gateway).await;
+
+        // Chunks of another request, and out of order ones, are both fine.
+        send_chunk(&gateway, "someone-else", 0, 1, 9);
+        send_chunk(&gateway, &nonce, 1, 2, 2);
+        send_chunk(&gateway, &nonce, 0, 2, 1);
+
+        let members = task.await.unwrap().unwrap();
+        let mut user_ids = members.members.iter().map(|member| member.user.id.get()).collect::<Vec<_>>();
+        user_ids.sort_unstable();
+        assert_eq!(user_ids, [1, 2]);
+
+        let mut not_found = members.not_found.iter().map(|id| id.get()).collect::<Vec<_>>();
+        not_found.sort_unstable();
+        assert_eq!(not_found, [101, 102]);
+        assert_eq!(members.presences.len(), 2);
+
+        assert!(requests.lock().unwrap().is_empty(), "the request is unregistered");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn times_out_waiting_for_chunks() {
+        let (sender, requests, mut gateway) = active_shard().await;
+        let (task, nonce) = request(&sender, &mut gateway).await;
+
+        send_chunk(&gateway, &nonce, 0, 2, 1);
+
+        let err = task.await.unwrap().unwrap_err();
+        assert!(matches!(err.kind(), MemberRequestErrorKind::TimedOut));
+        assert!(requests.lock().unwrap().is_empty(), "the request is unregistered");
+    }
+
+    #[tokio::test]
+    async fn unregisters_dropped_requests() {
+        let (sender, requests, mut gateway) = active_shard().await;
+        let (task, nonce) = request(&sender, &mut gateway).await;
+        assert!(requests.lock().unwrap().contains_key(&nonce));
+
+        task.abort();
+        assert!(task.await.unwrap_err().is_cancelled());
+        assert!(requests.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,58 @@
+use futures::Sink;
+use futures_core::Stream;
+use std::{error::Error, future::Future, pin::Pin};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{Error as WebsocketError, Message as WebsocketMessage},
+};
+
+
+/// Future resolving to a new connection.
+pub type ConnectFuture =
+    Pin<Box<dyn Future<Output = Result<Box<dyn Transport>, Box<dyn Error + Send + Sync>>> + Send>>;
+
+
+/// Connection to the gateway exchanging websocket messages.
+///
+/// Implemented for every matching `Stream + Sink`, such as tungstenite's
+/// `WebSocketStream` or in-memory channels.
+pub trait Transport:
+    Stream<Item = Result<WebsocketMessage, WebsocketError>>
+    + Sink<WebsocketMessage, Error = WebsocketError>
+    + Send
+    + Unpin
+{
+}
+
+
+impl<T> Transport for T where
+    T: Stream<Item = Result<WebsocketMessage, WebsocketError>>
+        + Sink<WebsocketMessage, Error = WebsocketError>
+        + Send
+        + Unpin
+{
+}
+
+
+/// Opens a shard's connections to the gateway.
+pub trait Connector: Send + Sync {
+    /// Connect to `url`, which already carries the gateway's query parameters.
+    fn connect(&self, url: &str) -> ConnectFuture;
+}
+
+
+/// Connects over TCP, TLS and WebSocket with tungstenite.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TungsteniteConnector;
+
+
+impl Connector for TungsteniteConnector {
+    fn connect(&self, url: &str) -> ConnectFuture {
+        let url = url.to_owned();
+
+        Box::pin(async move {
+            let (connection, _) = connect_async(url).await?;
+            Ok(Box::new(connection) as Box<dyn Transport>)
+        })
+    }
+}
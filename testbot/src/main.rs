@@ -1,4 +1,3 @@
-use tokio;
 use anyhow::{Context, Result};
 use dotenv::dotenv;
 
@@ -26,7 +25,7 @@ async fn main() -> Result<()> {
         token,
         ShardId::new(0, 1),
         Intents::GUILDS,
-    );
+    ).context("Failed to create shard")?;
 
     let mut cache = Cache::from_url(redis_url).await?;
 